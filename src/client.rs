@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use reqwest::{header::HeaderMap, redirect, Certificate, Client, Proxy};
+use std::{fs, path::PathBuf, time::Duration};
+use url::Url;
+
+/// The User-Agent sent with every request unless configured otherwise.
+pub const DEFAULT_USER_AGENT: &str = concat!("spdrs/", env!("CARGO_PKG_VERSION"));
+
+/// How to build the HTTP client shared by every request in a crawl.
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    pub user_agent: String,
+    /// Headers sent with every request, e.g. for authentication.
+    pub headers: HeaderMap,
+    /// The most redirects to follow for one request. With 0, the crawler
+    /// reports each redirect and queues its target like a link instead.
+    pub max_redirects: usize,
+    /// Send every request through this proxy instead of any configured in
+    /// the environment.
+    pub proxy: Option<Url>,
+    /// Trust these PEM encoded certificates as well as the system's.
+    pub root_certificates: Vec<PathBuf>,
+    /// Skip TLS certificate validation. Only use this on sites you control.
+    pub accept_invalid_certs: bool,
+    /// Ask for gzip, brotli and deflate compressed responses.
+    pub compression: bool,
+    /// Give up on a request that takes longer than this in total.
+    pub timeout: Option<Duration>,
+    /// Give up on a request if connecting takes longer than this.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+            max_redirects: 10,
+            proxy: None,
+            root_certificates: vec![],
+            accept_invalid_certs: false,
+            compression: true,
+            timeout: None,
+            connect_timeout: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub(crate) fn build(&self) -> Result<Client> {
+        let redirect_policy = match self.max_redirects {
+            0 => redirect::Policy::none(),
+            max => redirect::Policy::limited(max),
+        };
+
+        let mut client = Client::builder()
+            .user_agent(&self.user_agent)
+            .default_headers(self.headers.clone())
+            .redirect(redirect_policy)
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .gzip(self.compression)
+            .brotli(self.compression)
+            .deflate(self.compression);
+
+        if let Some(proxy) = &self.proxy {
+            client = client.proxy(Proxy::all(proxy.clone())?);
+        }
+        for path in &self.root_certificates {
+            let pem = fs::read(path)
+                .with_context(|| format!("Failed to read certificate {}", path.display()))?;
+            client = client.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+
+        Ok(client.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_builds() {
+        assert!(ClientConfig::default().build().is_ok());
+    }
+
+    #[test]
+    fn missing_certificate_is_an_error() {
+        let config = ClientConfig {
+            root_certificates: vec![PathBuf::from("does/not/exist.pem")],
+            ..ClientConfig::default()
+        };
+
+        assert!(config.build().is_err());
+    }
+
+    #[test]
+    fn proxy_config_builds() {
+        let config = ClientConfig {
+            proxy: Some(Url::parse("http://localhost:3128").expect("test URL should parse")),
+            ..ClientConfig::default()
+        };
+
+        assert!(config.build().is_ok());
+    }
+}
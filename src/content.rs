@@ -0,0 +1,140 @@
+use url::Url;
+
+/// The largest body downloaded unless configured otherwise, 10 MiB.
+pub const DEFAULT_MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Which responses to parse for links, how much of them to download, and
+/// which URLs aren't worth downloading at all.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentPolicy {
+    /// The media types to parse for links, like `text/html`, where `text/*`
+    /// matches any text type.
+    pub allow_types: Vec<String>,
+    /// The media types never to parse, even if allowed.
+    pub deny_types: Vec<String>,
+    /// The extensions of URLs to only ask for the headers of, with `HEAD`,
+    /// like `pdf` or `zip`. Servers that don't support `HEAD` are sent a `GET`
+    /// instead, whose body isn't read.
+    pub head_extensions: Vec<String>,
+    /// The most bytes of a body to download, after decompression, or `None`
+    /// for no limit.
+    pub max_body_size: Option<u64>,
+    /// Whether to parse the start of a body over the limit, rather than
+    /// failing the fetch.
+    pub truncate_large_bodies: bool,
+}
+
+impl Default for ContentPolicy {
+    fn default() -> Self {
+        Self {
+            allow_types: vec!["text/html".to_string(), "application/xhtml+xml".to_string()],
+            deny_types: vec![],
+            head_extensions: vec![],
+            max_body_size: Some(DEFAULT_MAX_BODY_SIZE),
+            truncate_large_bodies: false,
+        }
+    }
+}
+
+impl ContentPolicy {
+    /// Common extensions of images, media, documents and archives, which
+    /// never have links worth following.
+    pub const BINARY_EXTENSIONS: &'static [&'static str] = &[
+        "7z", "avi", "bmp", "bz2", "dmg", "doc", "docx", "exe", "flac", "gif", "gz", "ico", "iso",
+        "jpeg", "jpg", "m4a", "mkv", "mov", "mp3", "mp4", "ogg", "pdf", "png", "ppt", "pptx",
+        "rar", "svg", "tar", "tif", "tiff", "wav", "webm", "webp", "woff", "woff2", "xls", "xlsx",
+        "xz", "zip",
+    ];
+
+    /// Whether to parse a response with this `Content-Type` for links.
+    ///
+    /// Responses without one are parsed, as they always used to be.
+    pub(crate) fn should_parse(&self, content_type: Option<&str>) -> bool {
+        let Some(content_type) = content_type else {
+            return true;
+        };
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let matches = |pattern: &String| type_matches(pattern, &media_type);
+        self.allow_types.iter().any(matches) && !self.deny_types.iter().any(matches)
+    }
+
+    /// Whether to only ask for the headers of `url`, because of its extension.
+    pub(crate) fn head_only(&self, url: &Url) -> bool {
+        let file_name = url.path().rsplit('/').next().unwrap_or_default();
+        let Some((_, extension)) = file_name.rsplit_once('.') else {
+            return false;
+        };
+
+        self.head_extensions
+            .iter()
+            .any(|head| head.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Match a media type against a pattern like `text/html`, `text/*` or `*/*`.
+fn type_matches(pattern: &str, media_type: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+
+    match pattern.strip_suffix("/*") {
+        Some("*") => true,
+        Some(prefix) => media_type
+            .split_once('/')
+            .is_some_and(|(top, _)| top == prefix),
+        None => pattern == media_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com")
+            .and_then(|base| base.join(path))
+            .expect("test URL should parse")
+    }
+
+    #[test]
+    fn html_is_parsed_by_default() {
+        let policy = ContentPolicy::default();
+
+        assert!(policy.should_parse(Some("text/html; charset=utf-8")));
+        assert!(policy.should_parse(Some("Application/XHTML+XML")));
+        assert!(policy.should_parse(None));
+        assert!(!policy.should_parse(Some("image/png")));
+        assert!(!policy.should_parse(Some("application/pdf")));
+    }
+
+    #[test]
+    fn denied_types_win() {
+        let policy = ContentPolicy {
+            allow_types: vec!["text/*".to_string()],
+            deny_types: vec!["text/css".to_string()],
+            ..ContentPolicy::default()
+        };
+
+        assert!(policy.should_parse(Some("text/plain")));
+        assert!(!policy.should_parse(Some("text/css")));
+        assert!(!policy.should_parse(Some("application/json")));
+    }
+
+    #[test]
+    fn head_only_by_extension() {
+        let policy = ContentPolicy {
+            head_extensions: vec!["pdf".to_string(), ".ZIP".to_string()],
+            ..ContentPolicy::default()
+        };
+
+        assert!(policy.head_only(&url("/docs/manual.pdf")));
+        assert!(policy.head_only(&url("/downloads/archive.zip?version=2")));
+        assert!(!policy.head_only(&url("/docs/index.html")));
+        assert!(!policy.head_only(&url("/pdf/")));
+        assert!(!policy.head_only(&url("/v1.2/pdf")));
+    }
+}
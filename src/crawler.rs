@@ -0,0 +1,1677 @@
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::mpsc::UnboundedSender, task::JoinSet};
+use tracing::{debug, info, trace, warn};
+use url::Url;
+
+use crate::{
+    client::ClientConfig,
+    content::ContentPolicy,
+    fetch::{FetchError, Fetcher},
+    frontier::{Frontier, Job},
+    links::{
+        count_schemes, extract_links, mark_excluded, mark_internal, normalize_links,
+        partition_non_http, resolve_relative_paths, resolve_relative_schemes, Extracted, Link,
+    },
+    normalize::Normalization,
+    patterns::{UrlPattern, UrlRule, UrlRules},
+    politeness::Politeness,
+    retry::RetryPolicy,
+    robots::{MetaRobots, RobotsCache, DEFAULT_ROBOTS_AGENT},
+    scope::{HostRule, Scope},
+};
+
+/// The number of pages fetched at once unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// A crawled page and the in-scope links found on it.
+#[derive(Debug, PartialEq)]
+pub struct CrawlData {
+    pub url: String,
+    /// The page the URL was first found on, or `None` for a seed.
+    pub parent: Option<String>,
+    /// Where the page was finally fetched from, if the request was redirected.
+    pub redirect: Option<String>,
+    /// The number of clicks from the nearest seed, which is at depth 0.
+    pub depth: usize,
+    /// The HTTP status of the final attempt, if a response was received.
+    pub status: Option<u16>,
+    /// How many times the page was requested, including retries.
+    pub attempts: u32,
+    /// The `Content-Type` of the final response, if it had one.
+    pub content_type: Option<String>,
+    /// The text of the page's `<title>`, if it was parsed and had one.
+    pub title: Option<String>,
+    /// The size of the body in bytes, if it was downloaded or the server
+    /// said.
+    pub size: Option<u64>,
+    /// Whether only the start of the body was parsed, because it was over
+    /// the size limit.
+    pub truncated: bool,
+    /// How long the final attempt took to respond and send its body, if it
+    /// responded.
+    pub response_time: Option<Duration>,
+    /// Why the page couldn't be fetched, if it couldn't.
+    pub error: Option<FetchError>,
+    /// Every link to an HTTP(S) URL, including external ones and ones the
+    /// include and exclude rules exclude, which are never followed.
+    pub links: HashSet<Link>,
+    /// Links with schemes other than HTTP(S), like `mailto:`, if reporting
+    /// them is enabled.
+    pub non_http_links: HashSet<Link>,
+    /// The page's `noindex` and `nofollow` directives, in polite mode.
+    pub meta_robots: MetaRobots,
+}
+
+/// Settings shared by every task of a single crawl.
+#[derive(Debug)]
+struct Context {
+    fetcher: Fetcher,
+    scope: Scope,
+    url_rules: UrlRules,
+    normalization: Normalization,
+    report_non_http: bool,
+    max_depth: Option<usize>,
+    max_pages: Option<usize>,
+    pages: AtomicUsize,
+    /// `None` when robots.txt is being ignored.
+    robots: Option<RobotsCache>,
+    /// The agent to honor meta robots directives for, or `None` unless in
+    /// polite mode.
+    polite_agent: Option<String>,
+}
+
+impl Context {
+    /// Take a page from the budget, returning `false` if it has run out.
+    fn claim_page(&self) -> bool {
+        let claimed = self.pages.fetch_add(1, Ordering::SeqCst);
+
+        self.max_pages.is_none_or(|max| claimed < max)
+    }
+
+    fn budget_exhausted(&self) -> bool {
+        self.max_pages
+            .is_some_and(|max| self.pages.load(Ordering::SeqCst) >= max)
+    }
+}
+
+/// Builder for a [`Crawler`].
+#[derive(Debug, Default)]
+pub struct CrawlerBuilder {
+    seeds: Vec<Url>,
+    scope: Vec<HostRule>,
+    allowed_hosts: Vec<HostRule>,
+    same_site: bool,
+    url_rules: Vec<UrlRule>,
+    normalization: Normalization,
+    report_non_http: bool,
+    max_depth: Option<usize>,
+    max_pages: Option<usize>,
+    concurrency: Option<usize>,
+    requests_per_second: Option<f64>,
+    per_origin_concurrency: Option<usize>,
+    retry_policy: RetryPolicy,
+    content_policy: ContentPolicy,
+    client_config: ClientConfig,
+    user_agent: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    robots_agent: Option<String>,
+    ignore_robots: bool,
+    polite: bool,
+    read_timeout: Option<Duration>,
+    sink: Option<UnboundedSender<CrawlData>>,
+}
+
+impl CrawlerBuilder {
+    /// Add a URL to start crawling from.
+    pub fn seed(mut self, url: Url) -> Self {
+        self.seeds.push(url);
+        self
+    }
+
+    /// Add several URLs to start crawling from.
+    pub fn seeds(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.seeds.extend(urls);
+        self
+    }
+
+    /// Only follow links to hosts matching this rule, or any other rule
+    /// passed here.
+    ///
+    /// Defaults to the hosts of the seeds.
+    pub fn scope(mut self, rule: HostRule) -> Self {
+        self.scope.push(rule);
+        self
+    }
+
+    /// Also follow links to this host, on top of the scope.
+    pub fn allow_host(mut self, host: HostRule) -> Self {
+        self.allowed_hosts.push(host);
+        self
+    }
+
+    /// Also follow links to any host sharing a registrable domain with a
+    /// seed, e.g. `shop.example.co.uk` when crawling `www.example.co.uk`.
+    pub fn same_site(mut self, same_site: bool) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Follow links matching this pattern, unless an earlier rule excludes
+    /// them.
+    ///
+    /// Once any include rule is given, links matching no rule are excluded.
+    pub fn include(self, pattern: UrlPattern) -> Self {
+        self.url_rule(UrlRule::Include(pattern))
+    }
+
+    /// Don't follow links matching this pattern, unless an earlier rule
+    /// includes them.
+    pub fn exclude(self, pattern: UrlPattern) -> Self {
+        self.url_rule(UrlRule::Exclude(pattern))
+    }
+
+    /// Add a rule for which links to follow, after any added before it.
+    ///
+    /// Links are checked against the rules in order and the first matching
+    /// rule decides, e.g. excluding `/docs/archive/` then including `/docs/`
+    /// crawls everything under `/docs/` except the archive.
+    pub fn url_rule(mut self, rule: UrlRule) -> Self {
+        self.url_rules.push(rule);
+        self
+    }
+
+    /// Canonicalize seeds and links like this before deciding whether
+    /// they've been seen.
+    ///
+    /// Defaults to [`Normalization::default`].
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Whether to report links with schemes other than HTTP(S), like
+    /// `mailto:` and `javascript:`, which are never followed.
+    pub fn report_non_http(mut self, report_non_http: bool) -> Self {
+        self.report_non_http = report_non_http;
+        self
+    }
+
+    /// Don't follow links more than this many clicks away from a seed.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Stop after this many pages have been fetched.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Crawl with this many workers, so fetching at most this many pages at once.
+    ///
+    /// Defaults to [`DEFAULT_CONCURRENCY`].
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Send at most this many requests per second to any one origin.
+    ///
+    /// Origins that ask for a longer `Crawl-delay` in robots.txt get it.
+    pub fn requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.requests_per_second = Some(requests_per_second);
+        self
+    }
+
+    /// Fetch at most this many pages at once from any one origin.
+    pub fn per_origin_concurrency(mut self, per_origin_concurrency: usize) -> Self {
+        self.per_origin_concurrency = Some(per_origin_concurrency);
+        self
+    }
+
+    /// Retry failed fetches according to this policy.
+    ///
+    /// Defaults to [`RetryPolicy::default`].
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Only download and parse responses for links according to this policy.
+    ///
+    /// Defaults to [`ContentPolicy::default`], which only parses HTML.
+    pub fn content_policy(mut self, content_policy: ContentPolicy) -> Self {
+        self.content_policy = content_policy;
+        self
+    }
+
+    /// Download at most this many bytes of a body, after decompression.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BODY_SIZE`](crate::DEFAULT_MAX_BODY_SIZE).
+    /// Bodies over the limit fail to fetch, unless
+    /// [truncating them](Self::truncate_large_bodies).
+    pub fn max_body_size(mut self, max_body_size: u64) -> Self {
+        self.content_policy.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Whether to parse the start of a body over the size limit, rather than
+    /// failing to fetch it.
+    pub fn truncate_large_bodies(mut self, truncate_large_bodies: bool) -> Self {
+        self.content_policy.truncate_large_bodies = truncate_large_bodies;
+        self
+    }
+
+    /// Build the HTTP client from this config.
+    ///
+    /// Defaults to [`ClientConfig::default`]. The User-Agent and timeouts
+    /// set with their own methods win over the ones in it, whichever order
+    /// they're set in.
+    pub fn client_config(mut self, client_config: ClientConfig) -> Self {
+        self.client_config = client_config;
+        self
+    }
+
+    /// Identify as this User-Agent.
+    ///
+    /// Defaults to [`DEFAULT_USER_AGENT`](crate::DEFAULT_USER_AGENT).
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Look for this user-agent token in robots.txt files.
+    ///
+    /// Defaults to [`DEFAULT_ROBOTS_AGENT`].
+    pub fn robots_agent(mut self, robots_agent: impl Into<String>) -> Self {
+        self.robots_agent = Some(robots_agent.into());
+        self
+    }
+
+    /// Whether to crawl pages even if robots.txt disallows them.
+    ///
+    /// Only use this on sites you control.
+    pub fn ignore_robots(mut self, ignore_robots: bool) -> Self {
+        self.ignore_robots = ignore_robots;
+        self
+    }
+
+    /// Whether to honor `rel="nofollow"` on links and `noindex` and
+    /// `nofollow` directives in meta robots tags and `X-Robots-Tag` headers,
+    /// including those for the [robots agent](Self::robots_agent).
+    ///
+    /// Links marked `nofollow` are still reported, but not followed.
+    pub fn polite(mut self, polite: bool) -> Self {
+        self.polite = polite;
+        self
+    }
+
+    /// Give up on a request that takes longer than this in total.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if connecting takes longer than this.
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Give up on a request if reading the body takes longer than this,
+    /// once the response headers have arrived.
+    pub fn read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    /// Send a [`CrawlData`] record for every crawled page to this channel.
+    pub fn sink(mut self, sink: UnboundedSender<CrawlData>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// The client config, with the options set by their own methods applied.
+    fn resolved_client_config(&self) -> ClientConfig {
+        let mut client_config = self.client_config.clone();
+        if let Some(user_agent) = &self.user_agent {
+            client_config.user_agent = user_agent.clone();
+        }
+        if let Some(timeout) = self.timeout {
+            client_config.timeout = Some(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            client_config.connect_timeout = Some(connect_timeout);
+        }
+
+        client_config
+    }
+
+    pub fn build(self) -> Result<Crawler> {
+        if self.seeds.is_empty() {
+            bail!("At least one seed URL is required");
+        }
+        if let Some(seed) = self
+            .seeds
+            .iter()
+            .find(|seed| !matches!(seed.scheme(), "http" | "https"))
+        {
+            bail!("Only HTTP(S) URLs can be crawled, not {seed}");
+        }
+
+        let client_config = self.resolved_client_config();
+        let mut rules = self.scope;
+        if rules.is_empty() {
+            for seed in &self.seeds {
+                rules.push(HostRule::for_url(seed).ok_or(anyhow!("Missing host in {seed}"))?);
+            }
+        }
+        if self.same_site {
+            for seed in &self.seeds {
+                rules.extend(HostRule::same_site_as(seed));
+            }
+        }
+        rules.extend(self.allowed_hosts);
+
+        let concurrency = self.concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+        if concurrency == 0 {
+            bail!("Concurrency must be at least 1");
+        }
+        if self
+            .requests_per_second
+            .is_some_and(|rps| rps.is_nan() || rps <= 0.0)
+        {
+            bail!("Requests per second must be positive");
+        }
+        if self.per_origin_concurrency == Some(0) {
+            bail!("Per-origin concurrency must be at least 1");
+        }
+        if self.retry_policy.max_attempts == 0 {
+            bail!("Max attempts must be at least 1");
+        }
+
+        let robots_agent = self.robots_agent.as_deref().unwrap_or(DEFAULT_ROBOTS_AGENT);
+        let robots = (!self.ignore_robots).then(|| RobotsCache::new(robots_agent));
+        let polite_agent = self.polite.then(|| robots_agent.to_string());
+
+        let sink = self.sink.ok_or(anyhow!("An output sink is required"))?;
+
+        let seeds = self
+            .seeds
+            .iter()
+            .map(|seed| self.normalization.normalize(seed))
+            .collect();
+
+        Ok(Crawler {
+            seeds,
+            concurrency,
+            context: Arc::new(Context {
+                fetcher: Fetcher::new(
+                    client_config.build()?,
+                    Politeness::new(self.requests_per_second, self.per_origin_concurrency),
+                    self.retry_policy,
+                    self.content_policy,
+                    self.read_timeout,
+                ),
+                scope: Scope::new(rules),
+                url_rules: UrlRules::new(self.url_rules),
+                normalization: self.normalization,
+                report_non_http: self.report_non_http,
+                max_depth: self.max_depth,
+                max_pages: self.max_pages,
+                pages: AtomicUsize::new(0),
+                robots,
+                polite_agent,
+            }),
+            sink,
+        })
+    }
+}
+
+/// A configured crawl, ready to [`run`](Crawler::run).
+#[derive(Debug)]
+pub struct Crawler {
+    seeds: Vec<Url>,
+    concurrency: usize,
+    context: Arc<Context>,
+    sink: UnboundedSender<CrawlData>,
+}
+
+impl Crawler {
+    pub fn builder() -> CrawlerBuilder {
+        CrawlerBuilder::default()
+    }
+
+    /// Crawl from every seed until no unseen in-scope links remain.
+    ///
+    /// Pages that fail to crawl don't stop the crawl, they are sent to the
+    /// sink with their error and collected in the returned [`CrawlReport`].
+    /// The sink is closed once every page has been sent to it.
+    pub async fn run(self) -> Result<CrawlReport> {
+        debug!("restricting links to {:?}", self.context.scope.rules());
+        debug!("filtering links by {:?}", self.context.url_rules.rules());
+
+        let frontier = Arc::new(Frontier::default());
+        for url in self.seeds {
+            frontier.push(Job {
+                url,
+                depth: 0,
+                parent: None,
+            });
+        }
+
+        let mut workers = JoinSet::new();
+        for id in 0..self.concurrency {
+            workers.spawn(worker(
+                id,
+                frontier.clone(),
+                self.context.clone(),
+                self.sink.clone(),
+            ));
+        }
+        drop(self.sink);
+
+        let mut report = CrawlReport::default();
+        while let Some(worker_report) = workers.join_next().await {
+            // Returning early drops the join set, which aborts the other workers.
+            let worker_report = worker_report?;
+            report.pages += worker_report.pages;
+            report.excluded += worker_report.excluded;
+            for (scheme, count) in worker_report.non_http {
+                *report.non_http.entry(scheme).or_default() += count;
+            }
+            report.skipped.extend(worker_report.skipped);
+            report.errors.extend(worker_report.errors);
+        }
+
+        Ok(report)
+    }
+}
+
+/// A page that couldn't be crawled.
+#[derive(Debug)]
+pub struct CrawlError {
+    pub url: String,
+    pub error: anyhow::Error,
+}
+
+/// Why a URL was deliberately not crawled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkipReason {
+    DisallowedByRobots,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DisallowedByRobots => write!(f, "disallowed by robots.txt"),
+        }
+    }
+}
+
+/// A URL that was deliberately not crawled.
+#[derive(Debug, PartialEq)]
+pub struct SkippedUrl {
+    pub url: String,
+    pub reason: SkipReason,
+}
+
+/// A summary of a finished crawl.
+#[derive(Debug, Default)]
+pub struct CrawlReport {
+    /// The number of pages crawled successfully.
+    pub pages: usize,
+    /// The number of distinct in-scope URLs not followed because of the
+    /// include and exclude rules.
+    pub excluded: usize,
+    /// The number of links found with each scheme other than HTTP(S), if
+    /// reporting them is enabled.
+    pub non_http: BTreeMap<String, usize>,
+    pub skipped: Vec<SkippedUrl>,
+    pub errors: Vec<CrawlError>,
+}
+
+/// What became of a single job.
+enum Outcome {
+    /// The page was crawled, and this many newly seen links were excluded.
+    Crawled {
+        excluded: usize,
+        non_http: BTreeMap<String, usize>,
+    },
+    Failed(FetchError),
+    Skipped(SkipReason),
+    OverBudget,
+}
+
+async fn worker(
+    id: usize,
+    frontier: Arc<Frontier>,
+    context: Arc<Context>,
+    print_channel: UnboundedSender<CrawlData>,
+) -> CrawlReport {
+    let mut report = CrawlReport::default();
+
+    while let Some(job) = frontier.next().await {
+        let url = job.url.to_string();
+        match crawl(job, &context, &frontier, &print_channel).await {
+            Ok(Outcome::Crawled { excluded, non_http }) => {
+                report.pages += 1;
+                report.excluded += excluded;
+                for (scheme, count) in non_http {
+                    *report.non_http.entry(scheme).or_default() += count;
+                }
+            }
+            Ok(Outcome::Failed(error)) => {
+                warn!("Error crawling {url} ({error})");
+                report.errors.push(CrawlError {
+                    url,
+                    error: error.into(),
+                });
+            }
+            Ok(Outcome::Skipped(reason)) => {
+                info!("skipped {url} ({reason})");
+                report.skipped.push(SkippedUrl { url, reason });
+            }
+            Ok(Outcome::OverBudget) => {}
+            Err(error) => {
+                warn!("Error crawling {url} ({error})");
+                report.errors.push(CrawlError { url, error });
+            }
+        }
+        frontier.done();
+    }
+
+    trace!("worker {id} finished");
+    report
+}
+
+/// Crawl a single page, queueing up its unseen links.
+async fn crawl(
+    job: Job,
+    context: &Context,
+    frontier: &Frontier,
+    print_channel: &UnboundedSender<CrawlData>,
+) -> Result<Outcome> {
+    let Job { url, depth, parent } = job;
+    let parent = parent.as_ref().map(ToString::to_string);
+
+    let crawl_delay = match &context.robots {
+        Some(robots) => {
+            let rules = robots.rules(&context.fetcher, &url).await;
+            if !rules.is_allowed(&url) {
+                return Ok(Outcome::Skipped(SkipReason::DisallowedByRobots));
+            }
+
+            rules.crawl_delay
+        }
+        None => None,
+    };
+
+    if !context.claim_page() {
+        debug!("page budget exhausted, skipping {url}");
+        return Ok(Outcome::OverBudget);
+    }
+
+    let fetched = context.fetcher.fetch(&url, crawl_delay).await;
+    trace!("received");
+
+    let content_type = fetched.content_type().map(ToString::to_string);
+    let redirect = match &fetched.location {
+        // The client isn't following redirects, so follow this one like a link.
+        Some(location) => {
+            debug!("{url} redirects to {location}");
+            let location = context.normalization.normalize(location);
+            if !context.scope.contains(&location) || !context.url_rules.includes(&location) {
+                debug!("not following redirect to {location}, which is out of scope");
+            } else if frontier.push(Job {
+                url: location.clone(),
+                depth,
+                parent: Some(url.clone()),
+            }) {
+                debug!("queued {location}");
+            }
+
+            Some(location)
+        }
+        None => {
+            let final_url = fetched.url.filter(|final_url| *final_url != url);
+            if let Some(final_url) = &final_url {
+                debug!("{url} redirected to {final_url}");
+                frontier.claim(&context.normalization.normalize(final_url));
+            }
+
+            final_url
+        }
+    };
+
+    let unparsed = |error| CrawlData {
+        url: url.to_string(),
+        parent: parent.clone(),
+        redirect: redirect.as_ref().map(ToString::to_string),
+        depth,
+        status: fetched.status,
+        attempts: fetched.attempts,
+        content_type: content_type.clone(),
+        title: None,
+        size: fetched.size,
+        truncated: fetched.truncated,
+        response_time: fetched.response_time,
+        error,
+        links: HashSet::new(),
+        non_http_links: HashSet::new(),
+        meta_robots: MetaRobots::default(),
+    };
+
+    if fetched.truncated {
+        warn!("Only parsing the start of {url}, which is too large");
+    }
+
+    let resp_text = match &fetched.body {
+        Ok(Some(resp_text)) => resp_text,
+        Ok(None) => {
+            debug!("not parsing {url} ({content_type:?})");
+            debug!("sending crawl data for {url}");
+            print_channel.send(unparsed(None))?;
+
+            return Ok(Outcome::Crawled {
+                excluded: 0,
+                non_http: BTreeMap::new(),
+            });
+        }
+        Err(error) => {
+            debug!("sending crawl data for {url}");
+            print_channel.send(unparsed(Some(error.clone())))?;
+
+            return Ok(Outcome::Failed(error.clone()));
+        }
+    };
+
+    // Relative links are relative to where the page ended up, unless it
+    // says otherwise with a <base>.
+    let Extracted {
+        base,
+        links,
+        meta,
+        title,
+    } = extract_links(resp_text, redirect.as_ref().unwrap_or(&url));
+    debug!("extracted {links:?} relative to {base}");
+    let meta_robots = match &context.polite_agent {
+        Some(agent) => MetaRobots::parse(
+            meta.iter()
+                .map(|(name, content)| (name.as_str(), content.as_str())),
+            &fetched.headers,
+            agent,
+        ),
+        None => MetaRobots::default(),
+    };
+    let resolved_schemes = resolve_relative_schemes(&base, links);
+    let resolved_paths = resolve_relative_paths(&base, resolved_schemes);
+    let normalized = normalize_links(resolved_paths, &context.normalization);
+    let (http, non_http) = partition_non_http(normalized);
+    let non_http_links = if context.report_non_http {
+        non_http
+    } else {
+        HashSet::new()
+    };
+    let links = mark_excluded(mark_internal(http, &context.scope), &context.url_rules);
+    debug!("marked {links:?}");
+
+    // Claim excluded links so each one is only counted the first time it's seen.
+    let excluded = links
+        .iter()
+        .filter(|link| link.excluded)
+        .filter_map(|link| Url::parse(&link.url).ok())
+        .filter(|url| frontier.claim(url))
+        .count();
+    if excluded > 0 {
+        debug!("excluded {excluded} new links from {url}");
+    }
+
+    // The same URL can be linked from several elements, but is only followed once.
+    let to_follow: HashSet<String> = links
+        .iter()
+        .filter(|link| link.internal && !link.excluded && !link.is_form_action())
+        .filter(|link| context.polite_agent.is_none() || !link.has_rel("nofollow"))
+        .map(|link| link.url.clone())
+        .collect();
+
+    let crawl_data = CrawlData {
+        url: url.to_string(),
+        parent,
+        redirect: redirect.as_ref().map(ToString::to_string),
+        depth,
+        status: fetched.status,
+        attempts: fetched.attempts,
+        content_type,
+        title,
+        size: fetched.size,
+        truncated: fetched.truncated,
+        response_time: fetched.response_time,
+        error: None,
+        links,
+        non_http_links,
+        meta_robots,
+    };
+    let non_http = count_schemes(&crawl_data.non_http_links);
+
+    debug!("sending crawl data for {url}");
+    print_channel.send(crawl_data)?;
+
+    if context.max_depth.is_some_and(|max| depth >= max) {
+        debug!("reached max depth at {url}, not following links");
+        return Ok(Outcome::Crawled { excluded, non_http });
+    }
+    if meta_robots.nofollow {
+        debug!("{url} asks not to follow its links");
+        return Ok(Outcome::Crawled { excluded, non_http });
+    }
+
+    for link in to_follow {
+        if context.budget_exhausted() {
+            debug!("page budget exhausted, not following any more links from {url}");
+            break;
+        }
+
+        let link_url = match Url::parse(&link) {
+            Ok(url) => url,
+            Err(error) => {
+                warn!("Error parsing {link} ({error})");
+
+                continue;
+            }
+        };
+
+        if frontier.push(Job {
+            url: link_url,
+            depth: depth + 1,
+            parent: Some(url.clone()),
+        }) {
+            debug!("queued {link}");
+        } else {
+            debug!("seen {link}, skipping...");
+        }
+    }
+
+    Ok(Outcome::Crawled { excluded, non_http })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn build_requires_a_seed() {
+        let (snd, _rcv) = unbounded_channel();
+
+        let res = Crawler::builder().sink(snd).build();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn build_requires_http_seeds() {
+        let (snd, _rcv) = unbounded_channel();
+        let url = Url::parse("ftp://example.com/").expect("test URL should parse");
+
+        let res = Crawler::builder().seed(url).sink(snd).build();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn client_options_survive_a_later_client_config() {
+        let builder = Crawler::builder()
+            .user_agent("testbot")
+            .timeout(Duration::from_secs(5))
+            .client_config(ClientConfig {
+                connect_timeout: Some(Duration::from_secs(2)),
+                ..ClientConfig::default()
+            });
+
+        let client_config = builder.resolved_client_config();
+
+        assert_eq!(client_config.user_agent, "testbot");
+        assert_eq!(client_config.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(client_config.connect_timeout, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn scope_defaults_to_seed_hosts() {
+        let (snd, _rcv) = unbounded_channel();
+        let urls = ["https://example.com/dir/", "http://localhost:8000/"]
+            .map(|url| Url::parse(url).expect("test URL should parse"));
+
+        let crawler = Crawler::builder()
+            .seeds(urls)
+            .sink(snd)
+            .build()
+            .expect("test crawler should build");
+
+        assert_eq!(
+            crawler.context.scope.rules(),
+            [
+                HostRule::Host("example.com".to_string()),
+                HostRule::HostPort("localhost".to_string(), 8000),
+            ]
+        );
+    }
+
+    #[test]
+    fn allowed_hosts_extend_the_scope() {
+        let (snd, _rcv) = unbounded_channel();
+        let url = Url::parse("https://www.example.co.uk/").expect("test URL should parse");
+
+        let crawler = Crawler::builder()
+            .seed(url)
+            .scope("*.example.co.uk".parse().expect("test rule should parse"))
+            .allow_host("cdn.example.net".parse().expect("test rule should parse"))
+            .same_site(true)
+            .sink(snd)
+            .build()
+            .expect("test crawler should build");
+
+        assert_eq!(
+            crawler.context.scope.rules(),
+            [
+                HostRule::Subdomains("example.co.uk".to_string()),
+                HostRule::RegistrableDomain("example.co.uk".to_string()),
+                HostRule::Host("cdn.example.net".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn seeds_are_normalized() {
+        let (snd, _rcv) = unbounded_channel();
+        let url = Url::parse("HTTPS://Example.com:443/page#top").expect("test URL should parse");
+
+        let crawler = Crawler::builder()
+            .seed(url)
+            .sink(snd)
+            .build()
+            .expect("test crawler should build");
+
+        assert_eq!(crawler.seeds[0].as_str(), "https://example.com/page");
+    }
+
+    #[test]
+    fn page_budget_runs_out() {
+        let (snd, _rcv) = unbounded_channel();
+        let url = Url::parse("https://example.com").expect("test URL should parse");
+
+        let crawler = Crawler::builder()
+            .seed(url)
+            .max_pages(2)
+            .sink(snd)
+            .build()
+            .expect("test crawler should build");
+
+        assert!(crawler.context.claim_page());
+        assert!(crawler.context.claim_page());
+        assert!(crawler.context.budget_exhausted());
+        assert!(!crawler.context.claim_page());
+    }
+}
+
+#[cfg(all(test, feature = "e2e"))]
+mod e2e_tests {
+    use super::*;
+    use crate::fetch::ErrorKind;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    /// Receive every record, with response times cleared once checked, as
+    /// they vary from run to run.
+    async fn receive_crawl_data(mut rcv: UnboundedReceiver<CrawlData>) -> Vec<CrawlData> {
+        let mut crawl_data = vec![];
+        while let Some(mut data) = rcv.recv().await {
+            assert_eq!(data.response_time.is_some(), data.status.is_some());
+            data.response_time = None;
+            crawl_data.push(data);
+        }
+
+        crawl_data
+    }
+
+    fn anchors<const N: usize>(urls: [&str; N]) -> HashSet<Link> {
+        relative_anchors(urls.map(|url| (url, url)))
+    }
+
+    /// Internal links from `<a href="...">Link</a>`, from each `href` to its
+    /// resolved URL.
+    fn relative_anchors<const N: usize>(links: [(&str, &str); N]) -> HashSet<Link> {
+        links
+            .into_iter()
+            .map(|(href, url)| Link {
+                url: url.to_string(),
+                text: Some("Link".to_string()),
+                internal: true,
+                ..Link::new(href, "a", "href")
+            })
+            .collect()
+    }
+
+    fn html() -> Option<String> {
+        Some("text/html".to_string())
+    }
+
+    /// The size of a page served by the e2e server.
+    fn size(path: &str) -> Option<u64> {
+        let metadata = std::fs::metadata(format!("resources/test-data/e2e-pages/{path}"))
+            .expect("test page should exist");
+
+        Some(metadata.len())
+    }
+
+    fn crawler(url: &str, snd: UnboundedSender<CrawlData>) -> Crawler {
+        let url = Url::parse(url).expect("test URL is parseable");
+
+        Crawler::builder()
+            .seed(url)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid")
+    }
+
+    #[tokio::test]
+    async fn no_links() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/no-links.html", snd);
+
+        let expected = vec![CrawlData {
+            url: "http://localhost:8000/no-links.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("no-links.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: HashSet::new(),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn recursive() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/recursive.html", snd);
+
+        let expected = vec![CrawlData {
+            url: "http://localhost:8000/recursive.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("recursive.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: anchors(["http://localhost:8000/recursive.html"]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn max_depth() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/depth-0.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .max_depth(1)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let expected = vec![
+            CrawlData {
+                url: "http://localhost:8000/depth-0.html".to_string(),
+                parent: None,
+                redirect: None,
+                depth: 0,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("depth-0.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: anchors(["http://localhost:8000/depth-1.html"]),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+            CrawlData {
+                url: "http://localhost:8000/depth-1.html".to_string(),
+                parent: Some("http://localhost:8000/depth-0.html".to_string()),
+                redirect: None,
+                depth: 1,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("depth-1.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: anchors(["http://localhost:8000/depth-2.html"]),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+        ];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn max_pages() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/depth-1.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .max_pages(1)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let expected = vec![CrawlData {
+            url: "http://localhost:8000/depth-1.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("depth-1.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: anchors(["http://localhost:8000/depth-2.html"]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn missing_page() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/missing.html", snd);
+
+        let report = crawler.run().await.expect("crawl should finish");
+
+        assert_eq!(report.pages, 0);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].url, "http://localhost:8000/missing.html");
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data.len(), 1);
+        assert_eq!(crawl_data[0].url, "http://localhost:8000/missing.html");
+        assert_eq!(crawl_data[0].status, Some(404));
+        assert_eq!(crawl_data[0].attempts, 1);
+        assert_eq!(
+            crawl_data[0].error.as_ref().map(|error| error.kind),
+            Some(ErrorKind::Status(404))
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_linked_twice_are_crawled_once() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/diamond.html", snd);
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let mut urls: Vec<_> = receive_crawl_data(rcv)
+            .await
+            .into_iter()
+            .map(|data| data.url)
+            .collect();
+        urls.sort();
+
+        assert_eq!(
+            urls,
+            [
+                "http://localhost:8000/diamond-bottom.html",
+                "http://localhost:8000/diamond-left.html",
+                "http://localhost:8000/diamond-right.html",
+                "http://localhost:8000/diamond.html",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn url_variants_are_crawled_once() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/variants.html", snd);
+
+        let expected = CrawlData {
+            url: "http://localhost:8000/variants.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("variants.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: relative_anchors([
+                ("no-links.html", "http://localhost:8000/no-links.html"),
+                (
+                    "no-links.html#section",
+                    "http://localhost:8000/no-links.html",
+                ),
+                (
+                    "HTTP://LOCALHOST:8000/no-links.html",
+                    "http://localhost:8000/no-links.html",
+                ),
+                (
+                    "./nested/../no-links.html",
+                    "http://localhost:8000/no-links.html",
+                ),
+                ("%6Eo-links.html", "http://localhost:8000/no-links.html"),
+            ]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        };
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data.len(), 2);
+        assert_eq!(crawl_data[0], expected);
+    }
+
+    #[tokio::test]
+    async fn robots_disallowed() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/robots.html", snd);
+
+        let expected = vec![CrawlData {
+            url: "http://localhost:8000/robots.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("robots.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: anchors(["http://localhost:8000/disallowed.html"]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }];
+
+        let report = crawler.run().await.expect("crawl should finish");
+
+        assert_eq!(
+            report.skipped,
+            [SkippedUrl {
+                url: "http://localhost:8000/disallowed.html".to_string(),
+                reason: SkipReason::DisallowedByRobots,
+            }]
+        );
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn url_rules_first_match_wins() {
+        let (snd, rcv) = unbounded_channel();
+        let url =
+            Url::parse("http://localhost:8000/rules/index.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .exclude(
+                "/rules/docs/archive/"
+                    .parse()
+                    .expect("test pattern is valid"),
+            )
+            .include("/rules/docs/".parse().expect("test pattern is valid"))
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let report = crawler.run().await.expect("crawl should finish");
+
+        assert_eq!(report.pages, 2);
+        assert_eq!(report.excluded, 2);
+
+        let crawl_data = receive_crawl_data(rcv).await;
+        let index = crawl_data
+            .iter()
+            .find(|data| data.url.ends_with("/rules/index.html"))
+            .expect("the seed should be crawled");
+        let mut excluded: Vec<_> = index
+            .links
+            .iter()
+            .filter(|link| link.excluded)
+            .map(|link| link.url.as_str())
+            .collect();
+        excluded.sort();
+        assert_eq!(index.links.len(), 3);
+        assert_eq!(
+            excluded,
+            [
+                "http://localhost:8000/rules/blog.html",
+                "http://localhost:8000/rules/docs/archive/old.html",
+            ]
+        );
+
+        let mut urls: Vec<_> = crawl_data.into_iter().map(|data| data.url).collect();
+        urls.sort();
+
+        assert_eq!(
+            urls,
+            [
+                "http://localhost:8000/rules/docs/intro.html",
+                "http://localhost:8000/rules/index.html",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn links_resolve_against_base_href() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/base/index.html", snd);
+
+        let expected = vec![
+            CrawlData {
+                url: "http://localhost:8000/base/index.html".to_string(),
+                parent: None,
+                redirect: None,
+                depth: 0,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("base/index.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: relative_anchors([(
+                    "page.html",
+                    "http://localhost:8000/base/docs/page.html",
+                )]),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+            CrawlData {
+                url: "http://localhost:8000/base/docs/page.html".to_string(),
+                parent: Some("http://localhost:8000/base/index.html".to_string()),
+                redirect: None,
+                depth: 1,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("base/docs/page.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: HashSet::new(),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+        ];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn redirects_are_followed() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/redirect", snd);
+
+        let expected = vec![
+            CrawlData {
+                url: "http://localhost:8000/redirect".to_string(),
+                parent: None,
+                redirect: Some("http://localhost:8000/redirect/".to_string()),
+                depth: 0,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("redirect/index.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: relative_anchors([(
+                    "page.html",
+                    "http://localhost:8000/redirect/page.html",
+                )]),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+            CrawlData {
+                url: "http://localhost:8000/redirect/page.html".to_string(),
+                parent: Some("http://localhost:8000/redirect".to_string()),
+                redirect: None,
+                depth: 1,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("redirect/page.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: HashSet::new(),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+        ];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn unfollowed_redirects_are_queued() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/redirect").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .client_config(ClientConfig {
+                max_redirects: 0,
+                ..ClientConfig::default()
+            })
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let expected = vec![
+            CrawlData {
+                url: "http://localhost:8000/redirect".to_string(),
+                parent: None,
+                redirect: Some("http://localhost:8000/redirect/".to_string()),
+                depth: 0,
+                status: Some(301),
+                attempts: 1,
+                content_type: None,
+                title: None,
+                size: Some(0),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: HashSet::new(),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+            CrawlData {
+                url: "http://localhost:8000/redirect/".to_string(),
+                parent: Some("http://localhost:8000/redirect".to_string()),
+                redirect: None,
+                depth: 0,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("redirect/index.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: relative_anchors([(
+                    "page.html",
+                    "http://localhost:8000/redirect/page.html",
+                )]),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+            CrawlData {
+                url: "http://localhost:8000/redirect/page.html".to_string(),
+                parent: Some("http://localhost:8000/redirect/".to_string()),
+                redirect: None,
+                depth: 1,
+                status: Some(200),
+                attempts: 1,
+                content_type: html(),
+                title: Some("Title".to_string()),
+                size: size("redirect/page.html"),
+                truncated: false,
+                response_time: None,
+                error: None,
+                links: HashSet::new(),
+                non_http_links: HashSet::new(),
+                meta_robots: MetaRobots::default(),
+            },
+        ];
+
+        let res = crawler.run().await;
+        assert!(res.is_ok());
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn non_http_links_are_reported_not_followed() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/non-http.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .max_depth(0)
+            .report_non_http(true)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let expected = vec![CrawlData {
+            url: "http://localhost:8000/non-http.html".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: html(),
+            title: Some("Title".to_string()),
+            size: size("non-http.html"),
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: relative_anchors([("no-links.html", "http://localhost:8000/no-links.html")]),
+            non_http_links: anchors([
+                "javascript:void(0)",
+                "mailto:sales@example.com",
+                "mailto:support@example.com",
+            ])
+            .into_iter()
+            .map(|link| Link {
+                internal: false,
+                ..link
+            })
+            .collect(),
+            meta_robots: MetaRobots::default(),
+        }];
+
+        let report = crawler.run().await.expect("crawl should finish");
+        assert_eq!(
+            report.non_http,
+            BTreeMap::from_iter([("javascript".to_string(), 1), ("mailto".to_string(), 2)])
+        );
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data, expected);
+    }
+
+    #[tokio::test]
+    async fn polite_mode_skips_nofollow_links() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/polite.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .polite(true)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let report = crawler.run().await.expect("crawl should finish");
+        assert_eq!(report.pages, 2);
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data[0].url, "http://localhost:8000/polite.html");
+        assert_eq!(
+            crawl_data[0].meta_robots,
+            MetaRobots {
+                noindex: true,
+                nofollow: false,
+            }
+        );
+        assert_eq!(crawl_data[0].links.len(), 2);
+        assert_eq!(crawl_data[1].url, "http://localhost:8000/no-links.html");
+    }
+
+    #[tokio::test]
+    async fn form_actions_are_reported_not_followed() {
+        let (snd, rcv) = unbounded_channel();
+        let crawler = crawler("http://localhost:8000/form.html", snd);
+
+        let report = crawler.run().await.expect("crawl should finish");
+        assert_eq!(report.pages, 2);
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert_eq!(crawl_data[0].url, "http://localhost:8000/form.html");
+        assert!(crawl_data[0].links.contains(&Link {
+            url: "http://localhost:8000/depth-2.html".to_string(),
+            internal: true,
+            ..Link::new("depth-2.html", "form", "action")
+        }));
+        assert_eq!(
+            crawl_data
+                .iter()
+                .map(|data| data.url.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "http://localhost:8000/form.html",
+                "http://localhost:8000/no-links.html"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn polite_mode_honors_meta_nofollow() {
+        let (snd, rcv) = unbounded_channel();
+        let url = Url::parse("http://localhost:8000/polite-nofollow.html")
+            .expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .polite(true)
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let report = crawler.run().await.expect("crawl should finish");
+        assert_eq!(report.pages, 1);
+
+        let crawl_data = receive_crawl_data(rcv).await;
+
+        assert!(crawl_data[0].meta_robots.nofollow);
+        assert_eq!(crawl_data[0].links.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn only_html_is_parsed() {
+        let (snd, rcv) = unbounded_channel();
+        let url =
+            Url::parse("http://localhost:8000/content/index.html").expect("test URL is parseable");
+        let crawler = Crawler::builder()
+            .seed(url)
+            .content_policy(ContentPolicy {
+                head_extensions: vec!["png".to_string()],
+                ..ContentPolicy::default()
+            })
+            .sink(snd)
+            .build()
+            .expect("test crawler is valid");
+
+        let report = crawler.run().await.expect("crawl should finish");
+        assert_eq!(report.pages, 3);
+
+        let mut crawl_data = receive_crawl_data(rcv).await;
+        crawl_data.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let [index, logo, notes] = &crawl_data[..] else {
+            panic!("expected 3 pages, got {crawl_data:?}");
+        };
+        assert_eq!(index.links.len(), 2);
+        assert_eq!(logo.url, "http://localhost:8000/content/logo.png");
+        assert_eq!(logo.content_type.as_deref(), Some("image/png"));
+        assert_eq!(logo.size, size("content/logo.png"));
+        assert!(logo.links.is_empty());
+        assert_eq!(notes.url, "http://localhost:8000/content/notes.txt");
+        assert_eq!(notes.content_type.as_deref(), Some("text/plain"));
+        assert!(notes.links.is_empty());
+    }
+}
@@ -0,0 +1,599 @@
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::{
+    header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, LOCATION},
+    Client, Method, Response, StatusCode,
+};
+use std::{fmt, time::Duration};
+use tokio::time::{self, Instant};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{content::ContentPolicy, politeness::Politeness, retry::RetryPolicy};
+
+/// The broad category of a failed fetch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The server responded with this non-success status.
+    Status(u16),
+    /// The request or reading its response took too long.
+    Timeout,
+    /// No usable response was received.
+    Network,
+    /// The body was larger than the configured limit.
+    TooLarge,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "HTTP {status}"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::Network => write!(f, "network error"),
+            Self::TooLarge => write!(f, "too large"),
+        }
+    }
+}
+
+/// Why a URL couldn't be fetched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FetchError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(error: reqwest::Error) -> Self {
+        let kind = match error.status() {
+            Some(status) => ErrorKind::Status(status.as_u16()),
+            None if error.is_timeout() => ErrorKind::Timeout,
+            None => ErrorKind::Network,
+        };
+
+        Self {
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The final outcome of fetching a URL, after any retries.
+#[derive(Debug)]
+pub(crate) struct Fetched {
+    /// Where the last response came from after redirects, if there was one.
+    pub(crate) url: Option<Url>,
+    /// Where the last response redirected to, if the client didn't follow
+    /// the redirect itself.
+    pub(crate) location: Option<Url>,
+    /// The status of the last response, if there was one.
+    pub(crate) status: Option<u16>,
+    /// The headers of the last response, or none if there wasn't one.
+    pub(crate) headers: HeaderMap,
+    /// The size of the body in bytes, if it was read or the server said.
+    pub(crate) size: Option<u64>,
+    /// Whether only the start of the body was read, because it was too large.
+    pub(crate) truncated: bool,
+    /// How long the last attempt took to respond and send its body, if it
+    /// responded.
+    pub(crate) response_time: Option<Duration>,
+    pub(crate) attempts: u32,
+    /// The body, or `None` if it wasn't downloaded because it isn't worth
+    /// parsing for links.
+    pub(crate) body: Result<Option<String>, FetchError>,
+}
+
+impl Fetched {
+    /// The `Content-Type` of the last response, if it had a readable one.
+    pub(crate) fn content_type(&self) -> Option<&str> {
+        self.headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
+/// Fetches pages with the crawl's shared client, politely.
+#[derive(Debug)]
+pub(crate) struct Fetcher {
+    pub(crate) client: Client,
+    pub(crate) politeness: Politeness,
+    retry_policy: RetryPolicy,
+    content_policy: ContentPolicy,
+    read_timeout: Option<Duration>,
+}
+
+impl Fetcher {
+    pub(crate) fn new(
+        client: Client,
+        politeness: Politeness,
+        retry_policy: RetryPolicy,
+        content_policy: ContentPolicy,
+        read_timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            client,
+            politeness,
+            retry_policy,
+            content_policy,
+            read_timeout,
+        }
+    }
+
+    /// Fetch the body of `url`, retrying according to the retry policy and
+    /// waiting at least `crawl_delay` since the last request to its origin.
+    ///
+    /// Only bodies the content policy says to parse are downloaded, and URLs
+    /// with extensions it says aren't worth downloading are only asked for
+    /// their headers.
+    pub(crate) async fn fetch(&self, url: &Url, crawl_delay: Option<Duration>) -> Fetched {
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let fetched = Fetched {
+                attempts,
+                ..self.attempt(url, crawl_delay).await
+            };
+
+            match &fetched.body {
+                Err(error) if self.retry_policy.should_retry(attempts, error) => {
+                    let delay = self.retry_policy.delay(attempts);
+                    warn!("Error fetching {url} ({error}), retrying in {delay:?}");
+                    time::sleep(delay).await;
+                }
+                _ => return fetched,
+            }
+        }
+    }
+
+    async fn attempt(&self, url: &Url, crawl_delay: Option<Duration>) -> Fetched {
+        let _permit = self.politeness.acquire(url, crawl_delay).await;
+
+        let method = if self.content_policy.head_only(url) {
+            Method::HEAD
+        } else {
+            Method::GET
+        };
+
+        debug!("fetching {url} with {method}");
+        let started = Instant::now();
+        let mut sent = self.send(method.clone(), url).await;
+        // Plenty of servers don't support HEAD, so ask for the whole response
+        // instead, and still only read the headers.
+        if let Ok(resp) = &sent {
+            let unsupported = matches!(
+                resp.status(),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            );
+            if method == Method::HEAD && unsupported {
+                debug!("fetching {url} with GET, as HEAD got {}", resp.status());
+                sent = self.send(Method::GET, url).await;
+            }
+        }
+        let resp = match sent {
+            Ok(resp) => resp,
+            Err(error) => {
+                return Fetched {
+                    url: None,
+                    location: None,
+                    status: None,
+                    headers: HeaderMap::new(),
+                    size: None,
+                    truncated: false,
+                    response_time: None,
+                    attempts: 1,
+                    body: Err(error.into()),
+                }
+            }
+        };
+
+        let final_url = Some(resp.url().clone());
+        let status = Some(resp.status().as_u16());
+        let headers = resp.headers().clone();
+        // Redirects only get this far when the client isn't following them.
+        let location = resp
+            .status()
+            .is_redirection()
+            .then(|| headers.get(LOCATION)?.to_str().ok())
+            .flatten()
+            .and_then(|location| resp.url().join(location).ok());
+        // `Response::content_length` is always zero for `HEAD`, so ask the
+        // header directly.
+        let mut size = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse().ok());
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let mut truncated = false;
+        let body = match resp.error_for_status() {
+            Ok(_) if method == Method::HEAD || location.is_some() => Ok(None),
+            Ok(_) if !self.content_policy.should_parse(content_type) => {
+                // Dropping the response stops the body from downloading.
+                debug!("not downloading {url} ({content_type:?})");
+                Ok(None)
+            }
+            Ok(resp) => self.read_body(resp).await.map(|body| {
+                size = Some(body.len);
+                truncated = body.truncated;
+                Some(decode(&body.bytes, content_type))
+            }),
+            Err(error) => Err(error.into()),
+        };
+
+        Fetched {
+            url: final_url,
+            location,
+            status,
+            headers,
+            size,
+            truncated,
+            response_time: Some(started.elapsed()),
+            attempts: 1,
+            body,
+        }
+    }
+
+    async fn send(&self, method: Method, url: &Url) -> reqwest::Result<Response> {
+        let resp = self.client.request(method, url.clone()).send().await?;
+        self.politeness.observe(url, &resp);
+        Ok(resp)
+    }
+
+    async fn read_body(&self, resp: Response) -> Result<Body, FetchError> {
+        let Some(read_timeout) = self.read_timeout else {
+            return self.read_limited(resp).await;
+        };
+
+        match time::timeout(read_timeout, self.read_limited(resp)).await {
+            Ok(body) => body,
+            Err(_) => Err(FetchError {
+                kind: ErrorKind::Timeout,
+                message: format!("timed out reading body after {read_timeout:?}"),
+            }),
+        }
+    }
+
+    async fn read_limited(&self, resp: Response) -> Result<Body, FetchError> {
+        read_limited(
+            resp,
+            self.content_policy.max_body_size,
+            self.content_policy.truncate_large_bodies,
+        )
+        .await
+    }
+}
+
+/// Read a body a chunk at a time, stopping once it's over `max` bytes and
+/// either keeping the start of it or failing.
+///
+/// The chunks are already decompressed, so a small compressed body can't
+/// expand past the limit either.
+pub(crate) async fn read_limited(
+    mut resp: Response,
+    max: Option<u64>,
+    truncate: bool,
+) -> Result<Body, FetchError> {
+    let too_large = |max| FetchError {
+        kind: ErrorKind::TooLarge,
+        message: format!("body larger than {max} bytes"),
+    };
+
+    // Don't start downloading something that's going to fail anyway.
+    if let (Some(max), Some(len)) = (max, resp.content_length()) {
+        if len > max && !truncate {
+            return Err(too_large(max));
+        }
+    }
+
+    let mut body = Body::default();
+    while let Some(chunk) = resp.chunk().await? {
+        body.len += chunk.len() as u64;
+        body.bytes.extend_from_slice(&chunk);
+
+        let Some(max) = max else {
+            continue;
+        };
+        if body.len > max {
+            if !truncate {
+                return Err(too_large(max));
+            }
+            body.bytes.truncate(max as usize);
+            body.len = max;
+            body.truncated = true;
+            break;
+        }
+    }
+
+    Ok(body)
+}
+
+/// A downloaded body, before it's decoded.
+#[derive(Debug, Default)]
+pub(crate) struct Body {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) len: u64,
+    pub(crate) truncated: bool,
+}
+
+/// Decode a body in the charset its `Content-Type` names, or else UTF-8,
+/// replacing anything invalid.
+fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| value.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    encoding.decode(bytes).0.into_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Serve a single connection by replying with `response`, then stalling.
+    pub(crate) fn stalling_server(response: impl AsRef<[u8]> + Send + 'static) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("test server should bind");
+        let addr = listener.local_addr().expect("test server has an address");
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("test server should accept");
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(response.as_ref());
+            thread::sleep(Duration::from_secs(5));
+        });
+
+        Url::parse(&format!("http://{addr}/")).expect("test URL is parseable")
+    }
+
+    /// A server answering one connection with each of `responses` in turn.
+    pub(crate) fn sequential_server(responses: Vec<&'static str>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("test server should bind");
+        let addr = listener.local_addr().expect("test server has an address");
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().expect("test server should accept");
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        Url::parse(&format!("http://{addr}/")).expect("test URL is parseable")
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        let url = stalling_server("");
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .expect("test client should build");
+        let fetcher = Fetcher::new(
+            client,
+            Politeness::new(None, None),
+            RetryPolicy::never(),
+            ContentPolicy::default(),
+            None,
+        );
+
+        let fetched = fetcher.fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::Timeout)
+        );
+        assert_eq!(fetched.status, None);
+    }
+
+    #[tokio::test]
+    async fn slow_bodies_time_out() {
+        let url = stalling_server("HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello");
+        let fetcher = Fetcher::new(
+            Client::new(),
+            Politeness::new(None, None),
+            RetryPolicy::never(),
+            ContentPolicy::default(),
+            Some(Duration::from_millis(100)),
+        );
+
+        let fetched = fetcher.fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::Timeout)
+        );
+        assert_eq!(fetched.status, Some(200));
+    }
+
+    fn limited_fetcher(max_body_size: u64, truncate_large_bodies: bool) -> Fetcher {
+        Fetcher::new(
+            Client::new(),
+            Politeness::new(None, None),
+            RetryPolicy::never(),
+            ContentPolicy {
+                max_body_size: Some(max_body_size),
+                truncate_large_bodies,
+                ..ContentPolicy::default()
+            },
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn large_bodies_fail() {
+        let url = stalling_server("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world");
+
+        let fetched = limited_fetcher(5, false).fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::TooLarge)
+        );
+        assert_eq!(fetched.status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn large_bodies_can_be_truncated() {
+        let url = stalling_server("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world");
+
+        let fetched = limited_fetcher(5, true).fetch(&url, None).await;
+
+        assert_eq!(fetched.body, Ok(Some("hello".to_string())));
+        assert_eq!(fetched.size, Some(5));
+        assert!(fetched.truncated);
+    }
+
+    #[tokio::test]
+    async fn decompressed_size_is_limited() {
+        let gzipped = include_bytes!("../resources/test-data/zeros.gz");
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            gzipped.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(gzipped);
+        let url = stalling_server(response);
+
+        let fetched = limited_fetcher(1000, false).fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::TooLarge)
+        );
+    }
+
+    #[tokio::test]
+    async fn unfollowed_redirects_have_a_location() {
+        let url = stalling_server(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: /moved/\r\nContent-Length: 0\r\n\r\n",
+        );
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("test client should build");
+        let fetcher = Fetcher::new(
+            client,
+            Politeness::new(None, None),
+            RetryPolicy::never(),
+            ContentPolicy::default(),
+            None,
+        );
+
+        let fetched = fetcher.fetch(&url, None).await;
+
+        assert_eq!(fetched.status, Some(301));
+        assert_eq!(
+            fetched.location,
+            Some(url.join("/moved/").expect("test URL should join"))
+        );
+        assert_eq!(fetched.body, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn unsupported_heads_fall_back_to_get() {
+        let url = sequential_server(vec![
+            "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nContent-Length: 11\r\n\
+             Connection: close\r\n\r\nhello world",
+        ])
+        .join("/manual.pdf")
+        .expect("test URL should join");
+        let fetcher = Fetcher::new(
+            Client::new(),
+            Politeness::new(None, None),
+            RetryPolicy::never(),
+            ContentPolicy {
+                head_extensions: vec!["pdf".to_string()],
+                ..ContentPolicy::default()
+            },
+            None,
+        );
+
+        let fetched = fetcher.fetch(&url, None).await;
+
+        assert_eq!(fetched.status, Some(200));
+        assert_eq!(fetched.size, Some(11));
+        assert_eq!(fetched.body, Ok(None));
+    }
+
+    #[test]
+    fn bodies_are_decoded_by_charset() {
+        assert_eq!(
+            decode(b"caf\xe9", Some("text/html; charset=ISO-8859-1")),
+            "café"
+        );
+        assert_eq!(decode("café".as_bytes(), Some("text/html")), "café");
+        assert_eq!(decode(b"caf\xe9", None), "caf\u{fffd}");
+    }
+}
+
+#[cfg(all(test, feature = "e2e"))]
+mod e2e_tests {
+    use super::*;
+
+    fn fetcher() -> Fetcher {
+        Fetcher::new(
+            Client::new(),
+            Politeness::new(None, None),
+            RetryPolicy::default(),
+            ContentPolicy::default(),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn fetch_local_root() {
+        let url = Url::parse("http://localhost:8000/").expect("test URL is parseable");
+        let fetched = fetcher().fetch(&url, None).await;
+
+        assert!(fetched.body.is_ok());
+        assert_eq!(fetched.status, Some(200));
+        assert_eq!(fetched.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let url = Url::parse("http://localhost:8000/missing.html").expect("test URL is parseable");
+        let fetched = fetcher().fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::Status(404))
+        );
+        assert_eq!(fetched.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn network_errors_are_retried() {
+        let url = Url::parse("http://localhost:1/").expect("test URL is parseable");
+        let fetched = fetcher().fetch(&url, None).await;
+
+        assert_eq!(
+            fetched.body.map_err(|error| error.kind),
+            Err(ErrorKind::Network)
+        );
+        assert_eq!(fetched.status, None);
+        assert_eq!(fetched.attempts, 3);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Mutex,
+};
+use tokio::sync::Notify;
+use url::Url;
+
+/// A URL waiting to be crawled.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Job {
+    pub(crate) url: Url,
+    pub(crate) depth: usize,
+    /// The page the URL was first found on, or `None` for a seed.
+    pub(crate) parent: Option<Url>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queue: VecDeque<Job>,
+    /// Every URL ever queued, whether it is waiting, in flight or done.
+    claimed: HashSet<String>,
+    in_flight: usize,
+}
+
+/// The queue of URLs still to be crawled, shared by all workers.
+///
+/// The crawl is finished once the queue is empty and no worker is still
+/// processing a job, since only in-flight jobs can add more work.
+#[derive(Debug, Default)]
+pub(crate) struct Frontier {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl Frontier {
+    /// Queue a job unless its URL has already been claimed by another.
+    ///
+    /// Returns whether the job was queued.
+    pub(crate) fn push(&self, job: Job) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.claimed.insert(job.url.to_string()) {
+                return false;
+            }
+            state.queue.push_back(job);
+        }
+        self.changed.notify_waiters();
+
+        true
+    }
+
+    /// Claim a URL without queueing it, e.g. because a redirect led to it.
+    ///
+    /// Returns whether the URL was newly claimed.
+    pub(crate) fn claim(&self, url: &Url) -> bool {
+        self.state.lock().unwrap().claimed.insert(url.to_string())
+    }
+
+    /// Wait for the next job, or `None` once the crawl is finished.
+    ///
+    /// Every job returned must be handed back with [`Frontier::done`].
+    pub(crate) async fn next(&self) -> Option<Job> {
+        loop {
+            // Register for wakeups before checking so we can't miss one.
+            let changed = self.changed.notified();
+
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(job) = state.queue.pop_front() {
+                    state.in_flight += 1;
+                    return Some(job);
+                }
+                if state.in_flight == 0 {
+                    return None;
+                }
+            }
+
+            changed.await;
+        }
+    }
+
+    /// Mark a job from [`Frontier::next`] as finished.
+    pub(crate) fn done(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(url: &str) -> Job {
+        Job {
+            url: Url::parse(url).expect("test URL should parse"),
+            depth: 0,
+            parent: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_frontier_is_finished() {
+        let frontier = Frontier::default();
+
+        assert_eq!(frontier.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn jobs_come_out_in_order() {
+        let frontier = Frontier::default();
+        frontier.push(job("https://example.com/a"));
+        frontier.push(job("https://example.com/b"));
+
+        assert_eq!(frontier.next().await, Some(job("https://example.com/a")));
+        assert_eq!(frontier.next().await, Some(job("https://example.com/b")));
+    }
+
+    #[tokio::test]
+    async fn urls_are_only_queued_once() {
+        let frontier = Frontier::default();
+
+        assert!(frontier.push(job("https://example.com/a")));
+        assert!(!frontier.push(job("https://example.com/a")));
+
+        assert_eq!(frontier.next().await, Some(job("https://example.com/a")));
+        frontier.done();
+
+        assert!(!frontier.push(job("https://example.com/a")));
+        assert_eq!(frontier.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn claimed_urls_are_not_queued() {
+        let frontier = Frontier::default();
+        let url = Url::parse("https://example.com/a").expect("test URL should parse");
+
+        assert!(frontier.claim(&url));
+        assert!(!frontier.claim(&url));
+        assert!(!frontier.push(job("https://example.com/a")));
+
+        assert_eq!(frontier.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn waits_for_in_flight_jobs() {
+        let frontier = std::sync::Arc::new(Frontier::default());
+        frontier.push(job("https://example.com/a"));
+        let first = frontier.next().await;
+        assert!(first.is_some());
+
+        let waiter = tokio::spawn({
+            let frontier = frontier.clone();
+            async move { frontier.next().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        frontier.push(job("https://example.com/b"));
+        frontier.done();
+
+        let next = waiter.await.expect("waiter should not panic");
+        assert_eq!(next, Some(job("https://example.com/b")));
+
+        frontier.done();
+        assert_eq!(frontier.next().await, None);
+    }
+}
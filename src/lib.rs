@@ -0,0 +1,33 @@
+//! spdrs - A simple webcrawler in Rust 🕷️ 🕸️
+//!
+//! Build a [`Crawler`] with [`Crawler::builder`], hand it the sending half of
+//! a channel and consume the [`CrawlData`] records from the receiving half,
+//! e.g. with [`output::printer`] or [`output::json_lines`].
+
+mod client;
+mod content;
+mod crawler;
+mod fetch;
+mod frontier;
+mod links;
+mod normalize;
+pub mod output;
+mod patterns;
+mod politeness;
+mod retry;
+mod robots;
+mod scope;
+
+pub use client::{ClientConfig, DEFAULT_USER_AGENT};
+pub use content::{ContentPolicy, DEFAULT_MAX_BODY_SIZE};
+pub use crawler::{
+    CrawlData, CrawlError, CrawlReport, Crawler, CrawlerBuilder, SkipReason, SkippedUrl,
+    DEFAULT_CONCURRENCY,
+};
+pub use fetch::{ErrorKind, FetchError};
+pub use links::Link;
+pub use normalize::{Normalization, TrailingSlash};
+pub use patterns::{UrlPattern, UrlRule};
+pub use retry::RetryPolicy;
+pub use robots::{MetaRobots, DEFAULT_ROBOTS_AGENT};
+pub use scope::HostRule;
@@ -0,0 +1,776 @@
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{BTreeMap, HashSet};
+use url::Url;
+
+use crate::{normalize::Normalization, patterns::UrlRules, scope::Scope};
+
+/// A link found on a page.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Link {
+    /// The URL the link resolves to.
+    pub url: String,
+    /// The URL as written on the page.
+    pub href: String,
+    /// The element the link was found on, e.g. `img`.
+    pub element: String,
+    /// The attribute the link was found in, e.g. `src`.
+    pub attribute: String,
+    /// The text of an `<a>`, or the `alt` of an `<area>`, with whitespace
+    /// collapsed.
+    pub text: Option<String>,
+    pub title: Option<String>,
+    /// The link types in the `rel` attribute, lowercased, like `nofollow`.
+    pub rel: Vec<String>,
+    /// Whether the link is to a host in the crawl's scope.
+    pub internal: bool,
+    /// Whether the link is internal but the include and exclude rules say
+    /// not to follow it.
+    pub excluded: bool,
+}
+
+impl Link {
+    /// The scheme of the link, like `https` or `mailto`, once it's resolved.
+    pub fn scheme(&self) -> Option<String> {
+        Url::parse(&self.url)
+            .ok()
+            .map(|url| url.scheme().to_string())
+    }
+
+    /// Whether the link is to an HTTP(S) URL, so could be crawled.
+    pub fn is_http(&self) -> bool {
+        Url::parse(&self.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+    }
+
+    /// Whether the `rel` attribute includes `link_type`, like `nofollow`.
+    pub fn has_rel(&self, link_type: &str) -> bool {
+        self.rel.iter().any(|rel| rel == link_type)
+    }
+
+    /// Whether the link is where a form is submitted to, which could change
+    /// something on the server, like logging out, so is never followed.
+    pub fn is_form_action(&self) -> bool {
+        (self.element.as_str(), self.attribute.as_str()) == ("form", "action")
+    }
+
+    pub(crate) fn new(url: &str, element: &str, attribute: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            href: url.to_string(),
+            element: element.to_string(),
+            attribute: attribute.to_string(),
+            text: None,
+            title: None,
+            rel: vec![],
+            internal: false,
+            excluded: false,
+        }
+    }
+
+    /// A link found in `attribute` of `element`, with the element's text,
+    /// title and link types.
+    fn from_element(url: &str, element: ElementRef, attribute: &str) -> Self {
+        let name = element.value().name();
+        let text = match name {
+            "a" => Some(collapse_whitespace(element.text())).filter(|text| !text.is_empty()),
+            "area" => element.attr("alt").map(|alt| collapse_whitespace([alt])),
+            _ => None,
+        };
+        let mut rel: Vec<String> = vec![];
+        for link_type in element
+            .attr("rel")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+        {
+            let link_type = link_type.to_ascii_lowercase();
+            if !rel.contains(&link_type) {
+                rel.push(link_type);
+            }
+        }
+
+        Self {
+            text,
+            title: element.attr("title").map(|title| title.trim().to_string()),
+            rel,
+            ..Self::new(url, name, attribute)
+        }
+    }
+}
+
+/// Join the words of `text` with single spaces.
+fn collapse_whitespace<'a>(text: impl IntoIterator<Item = &'a str>) -> String {
+    text.into_iter()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The elements and attributes holding a single URL.
+const URL_ATTRIBUTES: &[(&str, &str)] = &[
+    ("a", "href"),
+    ("area", "href"),
+    ("link", "href"),
+    ("img", "src"),
+    ("script", "src"),
+    ("iframe", "src"),
+    ("form", "action"),
+    ("video", "src"),
+    ("video", "poster"),
+    ("audio", "src"),
+    ("source", "src"),
+    ("object", "data"),
+    ("embed", "src"),
+];
+
+/// The elements with a `srcset` attribute holding several URLs.
+const SRCSET_ELEMENTS: &[&str] = &["img", "source"];
+
+/// The links found on a page, and the URL to resolve them against.
+#[derive(Debug)]
+pub(crate) struct Extracted {
+    /// The first valid `<base href>`, or else the URL of the page itself.
+    pub(crate) base: Url,
+    pub(crate) links: HashSet<Link>,
+    /// The name and content of every `<meta name="..." content="...">`.
+    pub(crate) meta: Vec<(String, String)>,
+    /// The text of the first `<title>`, with whitespace collapsed.
+    pub(crate) title: Option<String>,
+}
+
+/// Extract the links from the page at `url`.
+pub(crate) fn extract_links(text: &str, url: &Url) -> Extracted {
+    let mut base = None;
+    let mut links = HashSet::new();
+    let mut meta = vec![];
+    let mut title = None;
+    let selector = Selector::parse("*").expect("we can parse the universal selector");
+
+    let html = Html::parse_document(text);
+    for element in html.select(&selector) {
+        let name = element.value().name();
+
+        if name == "base" && base.is_none() {
+            // The base is itself relative to the page, and only a web URL
+            // makes sense to resolve links against.
+            base = element
+                .attr("href")
+                .and_then(|href| url.join(href.trim()).ok())
+                .filter(|base| matches!(base.scheme(), "http" | "https"));
+        }
+
+        for (_, attribute) in URL_ATTRIBUTES.iter().filter(|(el, _)| *el == name) {
+            if let Some(link) = element.attr(attribute) {
+                links.insert(Link::from_element(link.trim(), element, attribute));
+            }
+        }
+        if SRCSET_ELEMENTS.contains(&name) {
+            for link in element.attr("srcset").map(parse_srcset).unwrap_or_default() {
+                links.insert(Link::from_element(link, element, "srcset"));
+            }
+        }
+        if name == "title" && title.is_none() {
+            title = Some(collapse_whitespace(element.text()));
+        }
+        if name == "meta" {
+            if let (Some(meta_name), Some(content)) =
+                (element.attr("name"), element.attr("content"))
+            {
+                meta.push((meta_name.trim().to_string(), content.to_string()));
+            }
+        }
+        if name == "meta"
+            && element
+                .attr("http-equiv")
+                .is_some_and(|equiv| equiv.eq_ignore_ascii_case("refresh"))
+        {
+            if let Some(link) = element.attr("content").and_then(parse_refresh) {
+                links.insert(Link::from_element(link, element, "content"));
+            }
+        }
+    }
+
+    Extracted {
+        base: base.unwrap_or_else(|| url.clone()),
+        links,
+        meta,
+        title,
+    }
+}
+
+/// The URLs of the image candidates in a `srcset` attribute.
+///
+/// URLs can contain commas, so candidates are split on the whitespace after
+/// each URL rather than on commas, as in the HTML spec.
+fn parse_srcset(srcset: &str) -> Vec<&str> {
+    let mut urls = vec![];
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after) = rest.split_at(end);
+        rest = after;
+
+        // A URL ending in a comma has no descriptors.
+        let trimmed = url.trim_end_matches(',');
+        if !trimmed.is_empty() {
+            urls.push(trimmed);
+        }
+        if trimmed.len() < url.len() {
+            continue;
+        }
+
+        // Skip the descriptors, up to the next comma outside of parentheses.
+        let mut depth = 0usize;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth = depth.saturating_sub(1),
+                    ',' => return depth == 0,
+                    _ => {}
+                }
+                false
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        rest = &rest[end..];
+    }
+}
+
+/// The URL in the `content` of a `<meta http-equiv="refresh">`, like
+/// `5; url=/next.html`.
+fn parse_refresh(content: &str) -> Option<&str> {
+    let (_delay, rest) = content.trim_start().split_once([';', ','])?;
+    let rest = rest.trim_start();
+    let rest = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => rest[3..].trim_start(),
+        _ => rest,
+    };
+    let url = rest.strip_prefix('=').unwrap_or(rest).trim();
+    let url = match url.chars().next() {
+        Some(quote @ ('"' | '\'')) => url[1..].split(quote).next().unwrap_or_default(),
+        _ => url,
+    };
+
+    (!url.is_empty()).then_some(url)
+}
+
+/// Replace the URL of every link with `f` of it.
+fn map_urls(links: HashSet<Link>, f: impl Fn(String) -> String) -> HashSet<Link> {
+    links
+        .into_iter()
+        .map(|link| Link {
+            url: f(link.url),
+            ..link
+        })
+        .collect()
+}
+
+/// Split resolved links into HTTP(S) links and those with any other scheme,
+/// dropping any that still aren't valid URLs.
+pub(crate) fn partition_non_http(links: HashSet<Link>) -> (HashSet<Link>, HashSet<Link>) {
+    links
+        .into_iter()
+        .filter(|l| Url::parse(&l.url).is_ok())
+        .partition(Link::is_http)
+}
+
+/// Count links by scheme.
+pub fn count_schemes<'a>(links: impl IntoIterator<Item = &'a Link>) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for scheme in links.into_iter().filter_map(Link::scheme) {
+        *counts.entry(scheme).or_default() += 1;
+    }
+
+    counts
+}
+
+/// Mark each link as internal if it's in scope, or external if not.
+pub(crate) fn mark_internal(links: HashSet<Link>, scope: &Scope) -> HashSet<Link> {
+    links
+        .into_iter()
+        .map(|link| Link {
+            internal: Url::parse(&link.url).is_ok_and(|url| scope.contains(&url)),
+            ..link
+        })
+        .collect()
+}
+
+/// Canonicalize absolute links, leaving any that don't parse alone.
+pub(crate) fn normalize_links(
+    links: HashSet<Link>,
+    normalization: &Normalization,
+) -> HashSet<Link> {
+    map_urls(links, |l| match Url::parse(&l) {
+        Ok(url) => normalization.normalize(&url).to_string(),
+        _ => l,
+    })
+}
+
+/// Mark the internal links the rules exclude, so they're reported but not
+/// followed.
+pub(crate) fn mark_excluded(links: HashSet<Link>, rules: &UrlRules) -> HashSet<Link> {
+    links
+        .into_iter()
+        .map(|link| Link {
+            excluded: link.internal && !Url::parse(&link.url).is_ok_and(|url| rules.includes(&url)),
+            ..link
+        })
+        .collect()
+}
+
+pub(crate) fn resolve_relative_paths(base: &Url, links: HashSet<Link>) -> HashSet<Link> {
+    map_urls(links, |l| match base.join(&l) {
+        Ok(url) => url.to_string(),
+        _ => l,
+    })
+}
+
+pub(crate) fn resolve_relative_schemes(base: &Url, links: HashSet<Link>) -> HashSet<Link> {
+    map_urls(links, |l| {
+        if l.starts_with("//") {
+            match Url::parse(&format!("{}:{l}", base.scheme())) {
+                Ok(url) => url.to_string(),
+                _ => l,
+            }
+        } else {
+            l
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::UrlRule;
+
+    fn page() -> Url {
+        Url::parse("https://example.com/dir/page.html").expect("test URL should parse")
+    }
+
+    fn anchors<const N: usize>(urls: [&str; N]) -> HashSet<Link> {
+        urls.into_iter()
+            .map(|url| Link::new(url, "a", "href"))
+            .collect()
+    }
+
+    /// Anchors with the text `text`, from each `href` to its resolved URL.
+    fn resolved_anchors<const N: usize>(
+        text: Option<&str>,
+        links: [(&str, &str); N],
+    ) -> HashSet<Link> {
+        links
+            .into_iter()
+            .map(|(href, url)| Link {
+                url: url.to_string(),
+                text: text.map(ToString::to_string),
+                ..Link::new(href, "a", "href")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_links() {
+        let text = "nothing to see here";
+        let expected = HashSet::new();
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn single_link() {
+        let text = r#"<a href="https://wikipedia.org">Link</a>"#;
+        let expected = resolved_anchors(
+            Some("Link"),
+            [("https://wikipedia.org", "https://wikipedia.org")],
+        );
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn simple_html() {
+        let text = r#"
+<a href="https://wikipedia.org"/>
+<a href="https://wikipedia.org/index.html"/>
+"#;
+        let expected = anchors(["https://wikipedia.org", "https://wikipedia.org/index.html"]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn html_with_multiple_links_to_a_line() {
+        let text =
+            r#"<a href="https://wikipedia.org"/><a href="https://wikipedia.org/index.html"/>"#;
+        let expected = anchors(["https://wikipedia.org", "https://wikipedia.org/index.html"]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn links_from_every_url_attribute() {
+        let text = r#"
+<link rel="stylesheet" href="style.css">
+<script src="app.js"></script>
+<img src="logo.png">
+<iframe src="embed.html"></iframe>
+<map><area href="region.html"></map>
+<form action="/search"></form>
+<video src="clip.mp4" poster="poster.jpg"><source src="clip.webm"></video>
+<audio src="song.ogg"></audio>
+<object data="doc.pdf"></object>
+<embed src="movie.swf">
+"#;
+        let expected = HashSet::from_iter([
+            Link {
+                rel: vec!["stylesheet".to_string()],
+                ..Link::new("style.css", "link", "href")
+            },
+            Link::new("app.js", "script", "src"),
+            Link::new("logo.png", "img", "src"),
+            Link::new("embed.html", "iframe", "src"),
+            Link::new("region.html", "area", "href"),
+            Link::new("/search", "form", "action"),
+            Link::new("clip.mp4", "video", "src"),
+            Link::new("poster.jpg", "video", "poster"),
+            Link::new("clip.webm", "source", "src"),
+            Link::new("song.ogg", "audio", "src"),
+            Link::new("doc.pdf", "object", "data"),
+            Link::new("movie.swf", "embed", "src"),
+        ]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn same_url_from_different_elements() {
+        let text = r#"<a href="logo.png">Logo</a><img src="logo.png">"#;
+        let expected = HashSet::from_iter([
+            Link {
+                text: Some("Logo".to_string()),
+                ..Link::new("logo.png", "a", "href")
+            },
+            Link::new("logo.png", "img", "src"),
+        ]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn link_text_title_and_rel() {
+        let text = r#"
+<a href="/pricing" title=" Plans " rel="NoFollow sponsored nofollow">
+  See   our <b>pricing</b>
+</a>
+<a href="/home"><img src="logo.png" alt="Home"></a>
+<map><area href="/north" alt="North  wing" rel="ugc"></map>
+"#;
+        let expected = HashSet::from_iter([
+            Link {
+                text: Some("See our pricing".to_string()),
+                title: Some("Plans".to_string()),
+                rel: vec!["nofollow".to_string(), "sponsored".to_string()],
+                ..Link::new("/pricing", "a", "href")
+            },
+            Link::new("/home", "a", "href"),
+            Link::new("logo.png", "img", "src"),
+            Link {
+                text: Some("North wing".to_string()),
+                rel: vec!["ugc".to_string()],
+                ..Link::new("/north", "area", "href")
+            },
+        ]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+        assert!(links.iter().any(|link| link.has_rel("sponsored")));
+    }
+
+    #[test]
+    fn srcset_candidates() {
+        let text = r#"
+<img srcset="small.jpg 480w, large.jpg 1080w">
+<picture><source srcset="a,b.webp 1x,c.webp 2x"></picture>
+"#;
+        let expected = HashSet::from_iter([
+            Link::new("small.jpg", "img", "srcset"),
+            Link::new("large.jpg", "img", "srcset"),
+            Link::new("a,b.webp", "source", "srcset"),
+            Link::new("c.webp", "source", "srcset"),
+        ]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn parse_srcset_edge_cases() {
+        assert_eq!(parse_srcset(""), Vec::<&str>::new());
+        assert_eq!(parse_srcset("only.jpg"), ["only.jpg"]);
+        assert_eq!(
+            parse_srcset(" a.jpg, b.jpg , c.jpg 2x"),
+            ["a.jpg", "b.jpg", "c.jpg"]
+        );
+        assert_eq!(
+            parse_srcset("a.jpg (odd, descriptor) 1x, b.jpg"),
+            ["a.jpg", "b.jpg"]
+        );
+    }
+
+    #[test]
+    fn meta_refresh() {
+        let text = r#"<meta http-equiv="Refresh" content="5; URL='next.html'">"#;
+        let expected = HashSet::from_iter([Link::new("next.html", "meta", "content")]);
+
+        let links = extract_links(text, &page()).links;
+
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn parse_refresh_variants() {
+        assert_eq!(parse_refresh("0;url=next.html"), Some("next.html"));
+        assert_eq!(parse_refresh("0; next.html"), Some("next.html"));
+        assert_eq!(parse_refresh("3, URL = \"next.html\""), Some("next.html"));
+        assert_eq!(parse_refresh("30"), None);
+        assert_eq!(parse_refresh("0; url="), None);
+    }
+
+    #[test]
+    fn first_title() {
+        let text = "<title>\n  Home |\n  Example </title><svg><title>Icon</title></svg>";
+
+        let extracted = extract_links(text, &page());
+
+        assert_eq!(extracted.title.as_deref(), Some("Home | Example"));
+    }
+
+    #[test]
+    fn base_defaults_to_the_page() {
+        let extracted = extract_links(r#"<a href="other.html">Link</a>"#, &page());
+
+        assert_eq!(extracted.base, page());
+    }
+
+    #[test]
+    fn first_valid_base_wins() {
+        let text = r#"
+<head>
+<base target="_blank">
+<base href="javascript:void(0)">
+<base href="/docs/">
+<base href="/ignored/">
+</head>
+"#;
+
+        let extracted = extract_links(text, &page());
+
+        assert_eq!(extracted.base.as_str(), "https://example.com/docs/");
+    }
+
+    #[test]
+    fn links_resolve_against_the_base() {
+        let text = include_str!("../resources/test-data/base-href.html");
+        let expected = resolved_anchors(
+            Some("Link"),
+            [
+                ("intro.html", "https://docs.example.com/v2/intro.html"),
+                (
+                    "api/index.html",
+                    "https://docs.example.com/v2/api/index.html",
+                ),
+                ("../about.html", "https://docs.example.com/about.html"),
+                (
+                    "https://example.com/absolute.html",
+                    "https://example.com/absolute.html",
+                ),
+            ],
+        );
+
+        let extracted = extract_links(text, &page());
+        let resolved_schemes = resolve_relative_schemes(&extracted.base, extracted.links);
+        let resolved = resolve_relative_paths(&extracted.base, resolved_schemes);
+
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn non_http_links_are_classified() {
+        let text = r#"
+<a href="contact.html">Contact</a>
+<a href="mailto:sales@example.com">Email</a>
+<a href="MAILTO:support@example.com">Email</a>
+<a href="tel:+441234567890">Call</a>
+<a href="javascript:void(0)">Menu</a>
+<img src="data:image/png;base64,iVBORw0KGgo=">
+<a href="ftp://ftp.example.com/file.txt">Download</a>
+"#;
+        let extracted = extract_links(text, &page());
+        let resolved = resolve_relative_paths(&extracted.base, extracted.links);
+
+        let (http, other) = partition_non_http(resolved);
+
+        assert_eq!(
+            http,
+            resolved_anchors(
+                Some("Contact"),
+                [("contact.html", "https://example.com/dir/contact.html")]
+            )
+        );
+        assert_eq!(
+            count_schemes(&other),
+            BTreeMap::from_iter([
+                ("data".to_string(), 1),
+                ("ftp".to_string(), 1),
+                ("javascript".to_string(), 1),
+                ("mailto".to_string(), 2),
+                ("tel".to_string(), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn mark_internal_links() {
+        let scope = Scope::new(vec!["example.com".parse().expect("test rule should parse")]);
+        let links = anchors([
+            "http://example.com",
+            "https://example.com/foo.jpg",
+            "http://wikipedia.org/bar.png",
+            "https://wikipedia.org/baz.gif",
+            "https://example.com.attacker.net/",
+            "https://example.community/",
+        ]);
+        let expected = HashSet::from(["http://example.com", "https://example.com/foo.jpg"]);
+
+        let marked = mark_internal(links, &scope);
+        let internal: HashSet<_> = marked
+            .iter()
+            .filter(|link| link.internal)
+            .map(|link| link.url.as_str())
+            .collect();
+
+        assert_eq!(marked.len(), 6);
+        assert_eq!(internal, expected);
+    }
+
+    #[test]
+    fn mark_links_excluded_by_pattern() {
+        let rules = UrlRules::new(vec![
+            UrlRule::Exclude(r"regex:\.pdf$".parse().expect("test pattern should parse")),
+            UrlRule::Include("/docs/".parse().expect("test pattern should parse")),
+        ]);
+        let mut links: HashSet<_> = anchors([
+            "https://example.com/docs/intro.html",
+            "https://example.com/docs/manual.pdf",
+            "https://example.com/blog/",
+        ])
+        .into_iter()
+        .map(|link| Link {
+            internal: true,
+            ..link
+        })
+        .collect();
+        links.insert(Link::new("https://example.org/blog/", "a", "href"));
+        let expected = HashSet::from([
+            "https://example.com/docs/manual.pdf",
+            "https://example.com/blog/",
+        ]);
+
+        let marked = mark_excluded(links, &rules);
+        let excluded: HashSet<_> = marked
+            .iter()
+            .filter(|link| link.excluded)
+            .map(|link| link.url.as_str())
+            .collect();
+
+        assert_eq!(marked.len(), 4);
+        assert_eq!(excluded, expected);
+    }
+
+    #[test]
+    fn links_can_be_normalized() {
+        let links = anchors([
+            "https://example.com/page",
+            "https://example.com/page#section",
+            "HTTPS://Example.com:443/%70age",
+            "not a URL",
+        ]);
+        let expected = resolved_anchors(
+            None,
+            [
+                ("https://example.com/page", "https://example.com/page"),
+                (
+                    "https://example.com/page#section",
+                    "https://example.com/page",
+                ),
+                ("HTTPS://Example.com:443/%70age", "https://example.com/page"),
+                ("not a URL", "not a URL"),
+            ],
+        );
+
+        let normalized = normalize_links(links, &Normalization::default());
+
+        assert_eq!(normalized, expected);
+    }
+
+    #[test]
+    fn relative_path_links_can_be_resolved() {
+        let url = Url::parse("https://example.com/dir/").expect("test URL should parse");
+        let links = anchors(["foo.jpg", "bar.png", "../baz.gif"]);
+        let expected = resolved_anchors(
+            None,
+            [
+                ("foo.jpg", "https://example.com/dir/foo.jpg"),
+                ("bar.png", "https://example.com/dir/bar.png"),
+                ("../baz.gif", "https://example.com/baz.gif"),
+            ],
+        );
+
+        let resolved = resolve_relative_paths(&url, links);
+
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn relative_scheme_links_can_be_resolved() {
+        let url = Url::parse("https://example.com").expect("test URL should parse");
+        let links = anchors([
+            "//www.example.com/",
+            "//example.com/foo.png",
+            "//wikipedia.org",
+        ]);
+        let expected = resolved_anchors(
+            None,
+            [
+                ("//www.example.com/", "https://www.example.com/"),
+                ("//example.com/foo.png", "https://example.com/foo.png"),
+                ("//wikipedia.org", "https://wikipedia.org/"),
+            ],
+        );
+
+        let resolved = resolve_relative_schemes(&url, links);
+
+        assert_eq!(resolved, expected);
+    }
+}
@@ -1,347 +1,458 @@
-use anyhow::{anyhow, bail, Result};
-use async_recursion::async_recursion;
-use once_cell::sync::Lazy;
-use scraper::{Html, Selector};
-use std::{
-    collections::HashSet,
-    env,
-    sync::{Arc, Mutex},
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use rusqlite::Connection;
+use spdrs::{
+    output::{
+        csv, dot, gexf, graphml, json_lines, mermaid, printer, sqlite, summary, GraphOptions,
+    },
+    ClientConfig, ContentPolicy, Crawler, HostRule, Normalization, RetryPolicy, TrailingSlash,
+    UrlPattern, UrlRule, DEFAULT_CONCURRENCY, DEFAULT_MAX_BODY_SIZE, DEFAULT_ROBOTS_AGENT,
+    DEFAULT_USER_AGENT,
 };
-use tokio::{
-    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-    task,
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
 };
-use tracing::{debug, trace, warn};
+use tokio::{sync::mpsc::unbounded_channel, task};
+use tracing::{info, level_filters::LevelFilter};
 use tracing_subscriber::EnvFilter;
 use url::Url;
 
-static SEEN: Lazy<Arc<Mutex<HashSet<String>>>> = Lazy::new(Arc::default);
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    /// Each page followed by an indented list of its links
+    Text,
+    /// A JSON object per page, one per line
+    Jsonl,
+    /// The link graph between pages in Graphviz DOT, written once the crawl finishes
+    Dot,
+    /// The link graph between pages as a Mermaid flowchart, written once the crawl finishes
+    Mermaid,
+    /// The link graph between pages, with page and link details, as GraphML
+    Graphml,
+    /// The link graph between pages, with page and link details, as GEXF
+    Gexf,
+    /// pages.csv and edges.csv tables, written to the --output directory
+    Csv,
+    /// Tables of pages, links, redirects and errors, added to the --output SQLite database
+    Sqlite,
+}
 
-#[derive(Debug, PartialEq)]
-struct CrawlData {
-    url: String,
-    links: HashSet<String>,
+/// A simple webcrawler 🕷️ 🕸️
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// URLs to start crawling from
+    #[arg(required = true)]
+    urls: Vec<Url>,
+
+    /// Only follow links to hosts matching this rule, like example.com, example.com:8080 or
+    /// *.example.com (may be repeated) [default: hosts of the start URLs]
+    #[arg(long, value_name = "RULE")]
+    scope: Vec<HostRule>,
+
+    /// Also follow links to hosts matching this rule (may be repeated)
+    #[arg(long, value_name = "RULE")]
+    allow_host: Vec<HostRule>,
+
+    /// Also follow links to any host under the same registrable domain as a start URL
+    #[arg(long)]
+    same_site: bool,
+
+    /// Don't follow links more than this many clicks away from a start URL
+    #[arg(short = 'd', long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Stop after fetching this many pages
+    #[arg(short = 'n', long, value_name = "N")]
+    max_pages: Option<usize>,
+
+    /// Crawl with this many workers, fetching at most this many pages at once
+    #[arg(short = 'c', long, value_name = "N", default_value_t = DEFAULT_CONCURRENCY)]
+    concurrency: usize,
+
+    /// Send at most this many requests per second to any one site
+    #[arg(short = 'r', long, value_name = "RPS")]
+    rate_limit: Option<f64>,
+
+    /// Fetch at most this many pages at once from any one site
+    #[arg(long, value_name = "N")]
+    per_origin_concurrency: Option<usize>,
+
+    /// Follow links matching this pattern, a path prefix like /docs/, glob:/docs/*.html or
+    /// regex:\.html$ (may be repeated, the first matching --include or --exclude wins)
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<UrlPattern>,
+
+    /// Don't follow links matching this pattern (may be repeated, the first matching --include
+    /// or --exclude wins)
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<UrlPattern>,
+
+    /// Sort query parameters before deciding whether a URL has been seen
+    #[arg(long)]
+    sort_query: bool,
+
+    /// Ignore this query parameter, where a trailing * matches any suffix, e.g. utm_* (may be
+    /// repeated)
+    #[arg(long, value_name = "NAME")]
+    strip_param: Vec<String>,
+
+    /// Ignore common tracking and session ID query parameters, like utm_* and fbclid
+    #[arg(long)]
+    strip_tracking_params: bool,
+
+    /// Keep, add or remove trailing slashes before deciding whether a URL has been seen
+    #[arg(long, value_name = "POLICY", default_value = "keep")]
+    trailing_slash: TrailingSlash,
+
+    /// List links with schemes other than HTTP(S), like mailto: and javascript:, for each page
+    #[arg(long)]
+    report_non_http: bool,
+
+    /// Parse responses of this media type for links, where text/* matches any text type (may be
+    /// repeated, defaults to text/html and application/xhtml+xml)
+    #[arg(long, value_name = "TYPE")]
+    parse_type: Vec<String>,
+
+    /// Never parse responses of this media type, even if --parse-type allows it (may be repeated)
+    #[arg(long, value_name = "TYPE")]
+    skip_type: Vec<String>,
+
+    /// Only request the headers of URLs with this extension, e.g. pdf (may be repeated)
+    #[arg(long, value_name = "EXT")]
+    head_extension: Vec<String>,
+
+    /// Only request the headers of URLs with common image, media, document and archive extensions
+    #[arg(long)]
+    head_binary: bool,
+
+    /// Download at most this many bytes of each response, after decompression
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_BODY_SIZE)]
+    max_body_size: u64,
+
+    /// Parse the start of responses over --max-body-size instead of treating them as errors
+    #[arg(long)]
+    truncate_large_bodies: bool,
+
+    /// Identify as this User-Agent
+    #[arg(short = 'A', long, default_value = DEFAULT_USER_AGENT)]
+    user_agent: String,
+
+    /// Send this header with every request, e.g. "Authorization: Bearer ..." (may be repeated)
+    #[arg(short = 'H', long, value_name = "HEADER", value_parser = parse_header)]
+    header: Vec<(HeaderName, HeaderValue)>,
+
+    /// Follow at most this many redirects per request, or with 0, report each redirect and crawl
+    /// its target as a separate page
+    #[arg(long, value_name = "N", default_value_t = ClientConfig::default().max_redirects)]
+    max_redirects: usize,
+
+    /// Send every request through this proxy
+    #[arg(long, value_name = "URL")]
+    proxy: Option<Url>,
+
+    /// Trust the PEM encoded certificate in this file (may be repeated)
+    #[arg(long, value_name = "PATH")]
+    ca_cert: Vec<PathBuf>,
+
+    /// Don't validate TLS certificates (only use on sites you control)
+    #[arg(long)]
+    insecure: bool,
+
+    /// Don't ask for compressed responses
+    #[arg(long)]
+    no_compression: bool,
+
+    /// Look for this user-agent token in robots.txt files and, with --polite, meta robots tags
+    #[arg(long, value_name = "TOKEN", default_value = DEFAULT_ROBOTS_AGENT)]
+    robots_agent: String,
+
+    /// Crawl pages even if robots.txt disallows them (only use on sites you control)
+    #[arg(long)]
+    ignore_robots: bool,
+
+    /// Don't follow rel="nofollow" links or links from pages whose meta robots tags or
+    /// X-Robots-Tag headers say nofollow, and mark pages that say noindex
+    #[arg(long)]
+    polite: bool,
+
+    /// Try fetching each page at most this many times [default: 3]
+    #[arg(long, value_name = "N")]
+    max_attempts: Option<u32>,
+
+    /// Wait this long before the first retry, doubling for each one after
+    #[arg(long, value_name = "MILLIS")]
+    retry_delay: Option<u64>,
+
+    /// Never wait longer than this between retries
+    #[arg(long, value_name = "MILLIS")]
+    max_retry_delay: Option<u64>,
+
+    /// Retry responses with this HTTP status (may be repeated) [default: 408 429 500 502 503 504]
+    #[arg(long, value_name = "CODE")]
+    retry_status: Vec<u16>,
+
+    /// Don't retry requests that got no response at all
+    #[arg(long)]
+    no_retry_network_errors: bool,
+
+    /// Give up on a request after this many seconds in total
+    #[arg(short = 't', long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Give up on a request if connecting takes longer than this many seconds
+    #[arg(long, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Give up on a request if reading the body takes longer than this many seconds
+    #[arg(long, value_name = "SECS")]
+    read_timeout: Option<u64>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// In graph formats, merge pages into one node per path prefix of this many segments
+    #[arg(long, value_name = "N")]
+    graph_collapse: Option<usize>,
+
+    /// In graph formats, leave out pages more than this many clicks from a seed
+    #[arg(long, value_name = "N")]
+    graph_depth: Option<usize>,
+
+    /// Write output to this file instead of stdout. Required with --format csv, where it's the
+    /// directory to write the tables to, and --format sqlite, where it's the database file
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        required_if_eq_any([("format", "csv"), ("format", "sqlite")])
+    )]
+    output: Option<PathBuf>,
+
+    /// Log more detail to stderr (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
 }
 
-async fn fetch(url: Url) -> Result<String> {
-    let resp_text = reqwest::get(url).await?.error_for_status()?.text().await?;
+fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or(anyhow!("expected \"Name: value\""))?;
 
-    Ok(resp_text)
+    Ok((name.trim().parse()?, value.trim().parse()?))
 }
 
-fn extract_links(text: &str) -> HashSet<String> {
-    let mut links = HashSet::new();
-    let a_selector = Selector::parse("a").expect("we can parse anchor links");
-    let li_selector = Selector::parse("link").expect("we can parse links");
-
-    let html = Html::parse_document(text);
-    for element in html.select(&a_selector).chain(html.select(&li_selector)) {
-        if let Some(link) = element.attr("href") {
-            links.insert(link.to_string());
+impl Args {
+    fn client_config(&self) -> ClientConfig {
+        ClientConfig {
+            user_agent: self.user_agent.clone(),
+            headers: HeaderMap::from_iter(self.header.iter().cloned()),
+            max_redirects: self.max_redirects,
+            proxy: self.proxy.clone(),
+            root_certificates: self.ca_cert.clone(),
+            accept_invalid_certs: self.insecure,
+            compression: !self.no_compression,
+            timeout: self.timeout.map(Duration::from_secs),
+            connect_timeout: self.connect_timeout.map(Duration::from_secs),
         }
     }
 
-    links
-}
+    fn retry_policy(&self) -> RetryPolicy {
+        let mut retry_policy = RetryPolicy::default();
+        if let Some(max_attempts) = self.max_attempts {
+            retry_policy.max_attempts = max_attempts;
+        }
+        if let Some(retry_delay) = self.retry_delay {
+            retry_policy.base_delay = Duration::from_millis(retry_delay);
+        }
+        if let Some(max_retry_delay) = self.max_retry_delay {
+            retry_policy.max_delay = Duration::from_millis(max_retry_delay);
+        }
+        if !self.retry_status.is_empty() {
+            retry_policy.retry_statuses = self.retry_status.clone();
+        }
+        retry_policy.retry_network_errors = !self.no_retry_network_errors;
 
-fn filter_external(links: HashSet<String>, allowed_subdomain: &str) -> HashSet<String> {
-    links
-        .into_iter()
-        .filter(|l| {
-            l.starts_with(&format!("http://{allowed_subdomain}"))
-                || l.starts_with(&format!("https://{allowed_subdomain}"))
-        })
-        .collect()
-}
+        retry_policy
+    }
 
-fn resolve_relative_paths(base: &Url, links: HashSet<String>) -> HashSet<String> {
-    links
-        .into_iter()
-        .map(|l| match base.join(&l) {
-            Ok(url) => url.to_string(),
-            _ => l,
-        })
-        .collect()
-}
+    fn content_policy(&self) -> ContentPolicy {
+        let mut content_policy = ContentPolicy::default();
+        if !self.parse_type.is_empty() {
+            content_policy.allow_types = self.parse_type.clone();
+        }
+        content_policy.deny_types = self.skip_type.clone();
+        content_policy.head_extensions = self.head_extension.clone();
+        content_policy.max_body_size = Some(self.max_body_size);
+        content_policy.truncate_large_bodies = self.truncate_large_bodies;
+        if self.head_binary {
+            content_policy.head_extensions.extend(
+                ContentPolicy::BINARY_EXTENSIONS
+                    .iter()
+                    .map(ToString::to_string),
+            );
+        }
 
-fn resolve_relative_schemes(base: &Url, links: HashSet<String>) -> HashSet<String> {
-    links
-        .into_iter()
-        .map(|l| {
-            if l.starts_with("//") {
-                match Url::parse(&format!("{}:{l}", base.scheme())) {
-                    Ok(url) => url.to_string(),
-                    _ => l,
-                }
-            } else {
-                l
-            }
-        })
-        .collect()
-}
+        content_policy
+    }
 
-#[async_recursion]
-async fn crawl(
-    url: Url,
-    allowed_subdomain: String,
-    print_channel: UnboundedSender<CrawlData>,
-) -> Result<()> {
-    debug!("fetching {url}");
-    let resp_text = fetch(url.clone()).await?;
-    trace!("received");
-
-    let links = extract_links(&resp_text);
-    debug!("extracted {links:?}");
-    let resolved_schemes = resolve_relative_schemes(&url, links);
-    let resolved_paths = resolve_relative_paths(&url, resolved_schemes);
-    let filtered = filter_external(resolved_paths, &allowed_subdomain);
-    debug!("filtered down to {filtered:?}");
-
-    let crawl_data = CrawlData {
-        url: url.to_string(),
-        links: filtered.iter().map(ToString::to_string).collect(),
-    };
+    fn normalization(&self) -> Normalization {
+        let mut strip_params = self.strip_param.clone();
+        if self.strip_tracking_params {
+            strip_params.extend(
+                Normalization::TRACKING_PARAMS
+                    .iter()
+                    .map(ToString::to_string),
+            );
+        }
 
-    debug!("sending crawl data for {url}");
-    print_channel.send(crawl_data)?;
+        Normalization {
+            sort_query: self.sort_query,
+            strip_params,
+            trailing_slash: self.trailing_slash,
+        }
+    }
 
-    SEEN.lock().unwrap().insert(url.to_string());
+    /// The include and exclude rules, in the order they were given.
+    fn url_rules(&self, matches: &ArgMatches) -> Vec<UrlRule> {
+        let indices = |id| matches.indices_of(id).into_iter().flatten();
+        let includes = indices("include").zip(self.include.iter().cloned().map(UrlRule::Include));
+        let excludes = indices("exclude").zip(self.exclude.iter().cloned().map(UrlRule::Exclude));
 
-    for link in filtered {
-        let url = match Url::parse(&link) {
-            Ok(url) => url,
-            Err(error) => {
-                warn!("Error parsing {link} ({error})");
+        let mut rules: Vec<_> = includes.chain(excludes).collect();
+        rules.sort_by_key(|(index, _)| *index);
 
-                continue;
-            }
-        };
+        rules.into_iter().map(|(_, rule)| rule).collect()
+    }
 
-        debug!("checking seen for {link}");
-        if SEEN.lock().unwrap().contains(&link.to_string()) {
-            debug!("seen {link}, skipping...");
-            continue;
+    fn graph_options(&self) -> GraphOptions {
+        GraphOptions {
+            collapse_segments: self.graph_collapse,
+            max_depth: self.graph_depth,
         }
-
-        debug!("not seen {link} yet, crawling...");
-        task::spawn(crawl(url, allowed_subdomain.clone(), print_channel.clone()));
     }
 
-    Ok(())
-}
-
-async fn printer(mut print_channel: UnboundedReceiver<CrawlData>) {
-    while let Some(data) = print_channel.recv().await {
-        let CrawlData { url, links } = data;
-        debug!("printer received crawl data for {url}");
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
 
-        println!("{url}");
-        for link in links {
-            println!("  * {link}");
+        match self.verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
         }
-        println!();
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        bail!("Usage: spdrs <url>");
-    }
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
 
     tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_writer(std::io::stderr)
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(args.log_level().into())
+                .from_env_lossy(),
+        )
+        .with_writer(io::stderr)
         .init();
 
-    let url_str = args
-        .get(1)
-        .expect("the index must exist due to previous len check");
-
-    let url = Url::parse(url_str)?;
-    let allowed_subdomain = url.host_str().ok_or(anyhow!("Missing host"))?;
-    debug!("restricting links to {allowed_subdomain}");
-
-    let (snd, rcv) = unbounded_channel();
-    let task_handle = task::spawn(async move { printer(rcv).await });
-
-    crawl(url.clone(), allowed_subdomain.to_string(), snd).await?;
-
-    task_handle.await.unwrap();
-
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn no_links() {
-        let text = "nothing to see here";
-        let expected = HashSet::new();
-
-        let links = extract_links(text);
-
-        assert_eq!(links, expected);
+    let url_rules = args.url_rules(&matches);
+    let graph_options = args.graph_options();
+    let mut builder = Crawler::builder()
+        .client_config(args.client_config())
+        .retry_policy(args.retry_policy())
+        .content_policy(args.content_policy())
+        .normalization(args.normalization())
+        .seeds(args.urls)
+        .concurrency(args.concurrency)
+        .robots_agent(args.robots_agent)
+        .ignore_robots(args.ignore_robots)
+        .polite(args.polite)
+        .same_site(args.same_site)
+        .report_non_http(args.report_non_http);
+    for rule in args.scope {
+        builder = builder.scope(rule);
     }
-
-    #[test]
-    fn single_link() {
-        let text = r#"<a href="https://wikipedia.org">Link</a>"#;
-        let expected = HashSet::from_iter(["https://wikipedia.org".to_string()]);
-
-        let links = extract_links(text);
-
-        assert_eq!(links, expected);
+    for rule in args.allow_host {
+        builder = builder.allow_host(rule);
     }
-
-    #[test]
-    fn simple_html() {
-        let text = r#"
-<a href="https://wikipedia.org"/>
-<a href="https://wikipedia.org/index.html"/>
-"#;
-        let expected = HashSet::from_iter([
-            "https://wikipedia.org".to_string(),
-            "https://wikipedia.org/index.html".to_string(),
-        ]);
-
-        let links = extract_links(text);
-
-        assert_eq!(links, expected);
+    if let Some(max_depth) = args.max_depth {
+        builder = builder.max_depth(max_depth);
     }
-
-    #[test]
-    fn html_with_multiple_links_to_a_line() {
-        let text =
-            r#"<a href="https://wikipedia.org"/><a href="https://wikipedia.org/index.html"/>"#;
-        let expected = HashSet::from_iter([
-            "https://wikipedia.org".to_string(),
-            "https://wikipedia.org/index.html".to_string(),
-        ]);
-
-        let links = extract_links(text);
-
-        assert_eq!(links, expected);
+    if let Some(max_pages) = args.max_pages {
+        builder = builder.max_pages(max_pages);
     }
-
-    #[test]
-    fn filter_external_links() {
-        let allowed_subdomain = "example.com";
-        let links = HashSet::from_iter([
-            "http://example.com".to_string(),
-            "https://example.com/foo.jpg".to_string(),
-            "http://wikipedia.org/bar.png".to_string(),
-            "https://wikipedia.org/baz.gif".to_string(),
-        ]);
-        let expected = HashSet::from_iter([
-            "http://example.com".to_string(),
-            "https://example.com/foo.jpg".to_string(),
-        ]);
-
-        let filtered = filter_external(links, allowed_subdomain);
-
-        assert_eq!(filtered, expected);
+    if let Some(rate_limit) = args.rate_limit {
+        builder = builder.requests_per_second(rate_limit);
     }
-
-    #[test]
-    fn relative_path_links_can_be_resolved() {
-        let url = Url::parse("https://example.com/dir/").expect("test URL should parse");
-        let links = HashSet::from_iter([
-            "foo.jpg".to_string(),
-            "bar.png".to_string(),
-            "../baz.gif".to_string(),
-        ]);
-        let expected = HashSet::from_iter([
-            "https://example.com/dir/foo.jpg".to_string(),
-            "https://example.com/dir/bar.png".to_string(),
-            "https://example.com/baz.gif".to_string(),
-        ]);
-
-        let resolved = resolve_relative_paths(&url, links);
-
-        assert_eq!(resolved, expected);
+    if let Some(per_origin_concurrency) = args.per_origin_concurrency {
+        builder = builder.per_origin_concurrency(per_origin_concurrency);
     }
-
-    #[test]
-    fn relative_scheme_links_can_be_resolved() {
-        let url = Url::parse("https://example.com").expect("test URL should parse");
-        let links = HashSet::from_iter([
-            "//www.example.com/".to_string(),
-            "//example.com/foo.png".to_string(),
-            "//wikipedia.org".to_string(),
-        ]);
-        let expected = HashSet::from_iter([
-            "https://www.example.com/".to_string(),
-            "https://example.com/foo.png".to_string(),
-            "https://wikipedia.org/".to_string(),
-        ]);
-
-        let resolved = resolve_relative_schemes(&url, links);
-
-        assert_eq!(resolved, expected);
+    if let Some(read_timeout) = args.read_timeout {
+        builder = builder.read_timeout(Duration::from_secs(read_timeout));
     }
-}
-
-#[cfg(all(test, feature = "e2e"))]
-mod e2e_tests {
-    use super::*;
-
-    async fn receive_crawl_data(mut rcv: UnboundedReceiver<CrawlData>) -> Vec<CrawlData> {
-        let mut crawl_data = vec![];
-        while let Some(data) = rcv.recv().await {
-            crawl_data.push(data);
-        }
-
-        crawl_data
-    }
-
-    #[tokio::test]
-    async fn fetch_local_root() {
-        let url = Url::parse("http://localhost:8000/").expect("test URL is parseable");
-        let res = fetch(url).await;
-
-        assert!(res.is_ok());
+    for rule in url_rules {
+        builder = builder.url_rule(rule);
     }
 
-    #[tokio::test]
-    async fn no_links() {
-        let (snd, rcv) = unbounded_channel();
-        let allowed_subdomain = "localhost:8000".to_string();
-        let url = Url::parse("http://localhost:8000/no-links.html").expect("test URL is parseable");
-
-        let expected = vec![CrawlData {
-            url: "http://localhost:8000/no-links.html".to_string(),
-            links: HashSet::new(),
-        }];
+    let (snd, rcv) = unbounded_channel();
+    let crawler = builder.sink(snd).build()?;
+    let task_handle: task::JoinHandle<Result<()>> = match (args.format, args.output) {
+        (Format::Csv, Some(dir)) => {
+            fs::create_dir_all(&dir)?;
+            let pages = BufWriter::new(File::create(dir.join("pages.csv"))?);
+            let edges = BufWriter::new(File::create(dir.join("edges.csv"))?);
+            task::spawn(async move { Ok(csv(rcv, pages, edges).await?) })
+        }
+        (Format::Sqlite, Some(path)) => {
+            let mut db = Connection::open(&path)?;
+            task::spawn(async move {
+                let run_id = sqlite(rcv, &mut db).await?;
+                info!("stored the crawl as run {run_id} in {}", path.display());
+                Ok(())
+            })
+        }
+        (format, output) => {
+            let out: Box<dyn Write + Send> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(io::stdout()),
+            };
+            task::spawn(async move {
+                Ok(match format {
+                    Format::Text => printer(rcv, out).await,
+                    Format::Jsonl => json_lines(rcv, out).await,
+                    Format::Dot => dot(rcv, out, &graph_options).await,
+                    Format::Mermaid => mermaid(rcv, out, &graph_options).await,
+                    Format::Graphml => graphml(rcv, out, &graph_options).await,
+                    Format::Gexf => gexf(rcv, out, &graph_options).await,
+                    Format::Csv | Format::Sqlite => {
+                        unreachable!("--output is required with --format {format:?}")
+                    }
+                }?)
+            })
+        }
+    };
 
-        let res = crawl(url, allowed_subdomain, snd).await;
-        assert!(res.is_ok());
+    let report = crawler.run().await?;
 
-        let crawl_data = receive_crawl_data(rcv).await;
+    task_handle.await??;
 
-        assert_eq!(crawl_data, expected);
+    if !args.quiet {
+        summary(&report, io::stderr())?;
     }
 
-    #[tokio::test]
-    async fn recursive() {
-        let (snd, rcv) = unbounded_channel();
-        let allowed_subdomain = "localhost:8000".to_string();
-        let url =
-            Url::parse("http://localhost:8000/recursive.html").expect("test URL is parseable");
-
-        let expected = vec![CrawlData {
-            url: "http://localhost:8000/recursive.html".to_string(),
-            links: HashSet::from_iter(["http://localhost:8000/recursive.html".to_string()]),
-        }];
-
-        let res = crawl(url, allowed_subdomain, snd).await;
-        assert!(res.is_ok());
-
-        let crawl_data = receive_crawl_data(rcv).await;
-
-        assert_eq!(crawl_data, expected);
-    }
+    Ok(())
 }
@@ -0,0 +1,270 @@
+use anyhow::{anyhow, Error, Result};
+use std::str::FromStr;
+use url::Url;
+
+/// What to do with a trailing slash at the end of a URL's path.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TrailingSlash {
+    /// Leave paths as they are.
+    #[default]
+    Keep,
+    /// Add a slash to paths whose last segment doesn't look like a file,
+    /// e.g. `/docs` becomes `/docs/` but `/docs/intro.html` is left alone.
+    Add,
+    /// Remove the slash from the end of any path other than `/`.
+    Remove,
+}
+
+impl FromStr for TrailingSlash {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy {
+            "keep" => Ok(Self::Keep),
+            "add" => Ok(Self::Add),
+            "remove" => Ok(Self::Remove),
+            _ => Err(anyhow!(
+                "Invalid trailing slash policy {policy:?}, expected keep, add or remove"
+            )),
+        }
+    }
+}
+
+/// How to canonicalize URLs so that variants of one URL are crawled once.
+///
+/// Parsing a URL already lowercases its scheme and host, removes default
+/// ports and resolves dot segments. On top of that fragments are always
+/// removed and percent-encoding is normalized.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Normalization {
+    /// Sort query parameters by name, keeping repeated ones in order.
+    pub sort_query: bool,
+    /// Remove query parameters with these names, where a trailing `*`
+    /// matches any suffix, e.g. `utm_*`.
+    pub strip_params: Vec<String>,
+    pub trailing_slash: TrailingSlash,
+}
+
+impl Normalization {
+    /// Common tracking and session parameters, for use in `strip_params`.
+    pub const TRACKING_PARAMS: &'static [&'static str] = &[
+        "utm_*",
+        "fbclid",
+        "gclid",
+        "msclkid",
+        "mc_cid",
+        "mc_eid",
+        "jsessionid",
+        "phpsessid",
+        "sid",
+    ];
+
+    /// The canonical form of `url`.
+    pub(crate) fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        url.set_fragment(None);
+
+        if !url.cannot_be_a_base() {
+            let path = normalize_percent_encoding(url.path());
+            let path = match self.trailing_slash {
+                TrailingSlash::Keep => path,
+                TrailingSlash::Add if !path.ends_with('/') && !looks_like_file(&path) => path + "/",
+                TrailingSlash::Add => path,
+                TrailingSlash::Remove if path.len() > 1 => path.trim_end_matches('/').to_string(),
+                TrailingSlash::Remove => path,
+            };
+            url.set_path(&path);
+        }
+
+        if let Some(query) = url.query() {
+            let query = normalize_percent_encoding(query);
+            let mut params: Vec<_> = query
+                .split('&')
+                .filter(|param| !param.is_empty())
+                .filter(|param| !self.is_stripped(param))
+                .collect();
+            if self.sort_query {
+                params.sort_by_key(|param| param.split('=').next());
+            }
+
+            let query = params.join("&");
+            url.set_query((!query.is_empty()).then_some(&query));
+        }
+
+        url
+    }
+
+    fn is_stripped(&self, param: &str) -> bool {
+        let name = param.split('=').next().unwrap_or_default();
+
+        self.strip_params
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name
+                    .get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+                None => name.eq_ignore_ascii_case(pattern),
+            })
+    }
+}
+
+/// Whether the last segment of `path` has an extension.
+fn looks_like_file(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .is_some_and(|segment| segment.contains('.'))
+}
+
+/// Decode percent-encoded unreserved characters and uppercase the hex digits
+/// of every other escape, as in RFC 3986 section 6.2.2.
+fn normalize_percent_encoding(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('%') {
+        normalized.push_str(&rest[..start]);
+        let escape = &rest[start..];
+
+        match escape
+            .get(1..3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(byte as char);
+            }
+            Some(_) => normalized.push_str(&escape[..3].to_ascii_uppercase()),
+            None => {
+                normalized.push('%');
+                rest = &escape[1..];
+                continue;
+            }
+        }
+        rest = &escape[3..];
+    }
+    normalized.push_str(rest);
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).expect("test URL should parse")
+    }
+
+    fn normalize(normalization: &Normalization, link: &str) -> String {
+        normalization.normalize(&url(link)).to_string()
+    }
+
+    #[test]
+    fn variants_are_normalized() {
+        let normalization = Normalization::default();
+        let expected = "http://example.com/a/page";
+
+        for variant in [
+            "http://example.com/a/page",
+            "http://example.com/a/page#section",
+            "HTTP://Example.COM:80/a/page",
+            "http://example.com/a/./b/../page",
+            "http://example.com/%61/%70age",
+        ] {
+            assert_eq!(normalize(&normalization, variant), expected, "{variant}");
+        }
+    }
+
+    #[test]
+    fn reserved_escapes_are_uppercased() {
+        assert_eq!(
+            normalize_percent_encoding("/a%2fb%c3%A9%7E%"),
+            "/a%2Fb%C3%A9~%"
+        );
+    }
+
+    #[test]
+    fn query_is_left_alone_by_default() {
+        assert_eq!(
+            normalize(&Normalization::default(), "http://example.com/?b=2&a=1"),
+            "http://example.com/?b=2&a=1"
+        );
+    }
+
+    #[test]
+    fn query_can_be_sorted() {
+        let normalization = Normalization {
+            sort_query: true,
+            ..Normalization::default()
+        };
+
+        assert_eq!(
+            normalize(&normalization, "http://example.com/?b=2&a=1&b=1"),
+            "http://example.com/?a=1&b=2&b=1"
+        );
+    }
+
+    #[test]
+    fn tracking_params_can_be_stripped() {
+        let normalization = Normalization {
+            strip_params: Normalization::TRACKING_PARAMS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            ..Normalization::default()
+        };
+
+        assert_eq!(
+            normalize(
+                &normalization,
+                "http://example.com/?id=1&utm_source=x&UTM_medium=y&PHPSESSID=z"
+            ),
+            "http://example.com/?id=1"
+        );
+        assert_eq!(
+            normalize(&normalization, "http://example.com/?utm_source=x"),
+            "http://example.com/"
+        );
+    }
+
+    #[test]
+    fn trailing_slashes_can_be_added() {
+        let normalization = Normalization {
+            trailing_slash: TrailingSlash::Add,
+            ..Normalization::default()
+        };
+
+        assert_eq!(
+            normalize(&normalization, "http://example.com/docs"),
+            "http://example.com/docs/"
+        );
+        assert_eq!(
+            normalize(&normalization, "http://example.com/docs/intro.html"),
+            "http://example.com/docs/intro.html"
+        );
+    }
+
+    #[test]
+    fn trailing_slashes_can_be_removed() {
+        let normalization = Normalization {
+            trailing_slash: TrailingSlash::Remove,
+            ..Normalization::default()
+        };
+
+        assert_eq!(
+            normalize(&normalization, "http://example.com/docs/"),
+            "http://example.com/docs"
+        );
+        assert_eq!(
+            normalize(&normalization, "http://example.com/"),
+            "http://example.com/"
+        );
+    }
+
+    #[test]
+    fn opaque_urls_only_lose_fragments() {
+        assert_eq!(
+            normalize(&Normalization::default(), "mailto:a@example.com#x"),
+            "mailto:a@example.com"
+        );
+    }
+}
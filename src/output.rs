@@ -0,0 +1,174 @@
+mod csv;
+mod graph;
+mod jsonl;
+mod sqlite;
+
+pub use self::csv::csv;
+pub use graph::{dot, gexf, graphml, mermaid, GraphOptions};
+pub use jsonl::json_lines;
+pub use sqlite::sqlite;
+
+use std::io::{self, Write};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+
+use crate::{
+    links::{count_schemes, Link},
+    CrawlData, CrawlReport, ErrorKind,
+};
+
+/// Write each page and its links to `out` as they arrive on the channel.
+///
+/// Pages are marked with why they couldn't be fetched, if they couldn't,
+/// whether they were truncated, and whether they asked not to be indexed or
+/// have their links followed.
+///
+/// Links from anything other than an `<a href>` are marked with where they
+/// were found, like `(img[src])`, links to other sites with `external` and
+/// links the include and exclude rules exclude with `excluded`.
+/// Links with schemes other than HTTP(S), if reported, are listed after a
+/// count of them by scheme.
+pub async fn printer(
+    mut print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+) -> io::Result<()> {
+    while let Some(data) = print_channel.recv().await {
+        let CrawlData {
+            url,
+            error,
+            links,
+            non_http_links,
+            meta_robots,
+            truncated,
+            ..
+        } = data;
+        debug!("printer received crawl data for {url}");
+
+        let mut notes = vec![];
+        if let Some(error) = error {
+            notes.push(format!("{}: {error}", error.kind));
+        }
+        if truncated {
+            notes.push("truncated".to_string());
+        }
+        if meta_robots.noindex {
+            notes.push("noindex".to_string());
+        }
+        if meta_robots.nofollow {
+            notes.push("nofollow".to_string());
+        }
+        if notes.is_empty() {
+            writeln!(out, "{url}")?;
+        } else {
+            writeln!(out, "{url} ({})", notes.join(", "))?;
+        }
+        for link in links {
+            write_link(&mut out, '*', &link, !link.internal)?;
+        }
+        if !non_http_links.is_empty() {
+            let counts = count_schemes(&non_http_links)
+                .into_iter()
+                .map(|(scheme, count)| format!("{count} {scheme}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(out, "  non-HTTP links: {counts}")?;
+            for link in non_http_links {
+                write_link(&mut out, '-', &link, false)?;
+            }
+        }
+        writeln!(out)?;
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Write what a finished crawl did to `out`: how many pages it crawled,
+/// excluded, skipped and failed, why each skipped URL was skipped, and how
+/// many links of each non-HTTP scheme it found, if any were reported.
+pub fn summary(report: &CrawlReport, mut out: impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "crawled {} pages, excluded {}, skipped {} and hit {} errors",
+        report.pages,
+        report.excluded,
+        report.skipped.len(),
+        report.errors.len()
+    )?;
+    for skipped in &report.skipped {
+        writeln!(out, "  skipped {} ({})", skipped.url, skipped.reason)?;
+    }
+    if !report.non_http.is_empty() {
+        let counts = report
+            .non_http
+            .iter()
+            .map(|(scheme, count)| format!("{count} {scheme}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "  non-HTTP links: {counts}")?;
+    }
+
+    out.flush()
+}
+
+/// The name of a kind of error in machine readable output, like `too_large`.
+fn error_kind(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Status(_) => "status",
+        ErrorKind::Timeout => "timeout",
+        ErrorKind::Network => "network",
+        ErrorKind::TooLarge => "too_large",
+    }
+}
+
+fn write_link(out: &mut impl Write, bullet: char, link: &Link, external: bool) -> io::Result<()> {
+    let mut notes = vec![];
+    if (link.element.as_str(), link.attribute.as_str()) != ("a", "href") {
+        notes.push(format!("{}[{}]", link.element, link.attribute));
+    }
+    if external {
+        notes.push("external".to_string());
+    }
+    if link.excluded {
+        notes.push("excluded".to_string());
+    }
+
+    if notes.is_empty() {
+        writeln!(out, "  {bullet} {}", link.url)
+    } else {
+        writeln!(out, "  {bullet} {} ({})", link.url, notes.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SkipReason, SkippedUrl};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn summary_lists_skipped_urls_and_non_http_links() {
+        let report = CrawlReport {
+            pages: 3,
+            excluded: 2,
+            non_http: BTreeMap::from([("mailto".to_string(), 2), ("tel".to_string(), 1)]),
+            skipped: vec![SkippedUrl {
+                url: "https://example.com/private/".to_string(),
+                reason: SkipReason::DisallowedByRobots,
+            }],
+            errors: vec![],
+        };
+
+        let mut out = vec![];
+        summary(&report, &mut out).expect("writing to a Vec works");
+
+        assert_eq!(
+            String::from_utf8(out).expect("the summary is UTF-8"),
+            "\
+crawled 3 pages, excluded 2, skipped 1 and hit 0 errors
+  skipped https://example.com/private/ (disallowed by robots.txt)
+  non-HTTP links: 2 mailto, 1 tel
+"
+        );
+    }
+}
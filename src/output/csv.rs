@@ -0,0 +1,235 @@
+//! Two CSV tables for spreadsheets, written as pages arrive.
+//!
+//! `pages.csv` has a row per crawled page:
+//!
+//! | column             | example                    |
+//! |--------------------|----------------------------|
+//! | `url`              | `https://example.com/`     |
+//! | `status`           | `200`                      |
+//! | `content_type`     | `text/html; charset=utf-8` |
+//! | `depth`            | `0`                        |
+//! | `size`             | `1256`                     |
+//! | `response_time_ms` | `84.2`                     |
+//! | `title`            | `Example Domain`           |
+//!
+//! `edges.csv` has a row per link found on a page, sorted by target within
+//! each page:
+//!
+//! | column     | example                     |
+//! |------------|-----------------------------|
+//! | `source`   | `https://example.com/`      |
+//! | `target`   | `https://example.com/about` |
+//! | `text`     | `About us`                  |
+//! | `rel`      | `nofollow noopener`         |
+//! | `internal` | `true`                      |
+//! | `excluded` | `false`                     |
+//!
+//! Missing values, like the status of a page that couldn't be fetched, are
+//! empty.
+
+use ::csv::{Writer, WriterBuilder};
+use serde::Serialize;
+use std::io::{self, Write};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+
+use crate::{CrawlData, Link};
+
+#[derive(Serialize)]
+struct PageRow<'a> {
+    url: &'a str,
+    status: Option<u16>,
+    content_type: Option<&'a str>,
+    depth: usize,
+    size: Option<u64>,
+    response_time_ms: Option<f64>,
+    title: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct EdgeRow<'a> {
+    source: &'a str,
+    target: &'a str,
+    text: Option<&'a str>,
+    rel: String,
+    internal: bool,
+    excluded: bool,
+}
+
+impl<'a> From<&'a CrawlData> for PageRow<'a> {
+    fn from(data: &'a CrawlData) -> Self {
+        Self {
+            url: &data.url,
+            status: data.status,
+            content_type: data.content_type.as_deref(),
+            depth: data.depth,
+            size: data.size,
+            response_time_ms: data
+                .response_time
+                .map(|response_time| response_time.as_secs_f64() * 1000.0),
+            title: data.title.as_deref(),
+        }
+    }
+}
+
+impl<'a> EdgeRow<'a> {
+    fn new(source: &'a str, link: &'a Link) -> Self {
+        Self {
+            source,
+            target: &link.url,
+            text: link.text.as_deref(),
+            rel: link.rel.join(" "),
+            internal: link.internal,
+            excluded: link.excluded,
+        }
+    }
+}
+
+/// Write each page to `pages` and its links to `edges`, as CSV with a
+/// header row, as they arrive on the channel.
+pub async fn csv(
+    mut print_channel: UnboundedReceiver<CrawlData>,
+    pages: impl Write,
+    edges: impl Write,
+) -> io::Result<()> {
+    // Write the headers up front, so they're there even if nothing was
+    // crawled, rather than with the first row.
+    let mut pages = headerless(pages);
+    let mut edges = headerless(edges);
+    pages.write_record([
+        "url",
+        "status",
+        "content_type",
+        "depth",
+        "size",
+        "response_time_ms",
+        "title",
+    ])?;
+    edges.write_record(["source", "target", "text", "rel", "internal", "excluded"])?;
+
+    while let Some(data) = print_channel.recv().await {
+        debug!("CSV writer received crawl data for {}", data.url);
+
+        pages.serialize(PageRow::from(&data))?;
+        let mut links: Vec<_> = data.links.iter().collect();
+        links.sort_by_key(|link| {
+            (
+                &link.url,
+                &link.href,
+                &link.element,
+                &link.attribute,
+                &link.text,
+            )
+        });
+        for link in links {
+            edges.serialize(EdgeRow::new(&data.url, link))?;
+        }
+        pages.flush()?;
+        edges.flush()?;
+    }
+
+    Ok(())
+}
+
+fn headerless<W: Write>(out: W) -> Writer<W> {
+    WriterBuilder::new().has_headers(false).from_writer(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MetaRobots;
+    use std::{collections::HashSet, time::Duration};
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[tokio::test]
+    async fn pages_and_edges_are_written_as_tables() {
+        let (snd, rcv) = unbounded_channel();
+        let about = Link {
+            url: "https://example.com/about".to_string(),
+            text: Some("About \"us\", and more".to_string()),
+            internal: true,
+            ..Link::new("/about", "a", "href")
+        };
+        let elsewhere = Link {
+            rel: vec!["nofollow".to_string(), "noopener".to_string()],
+            ..Link::new("https://example.org/", "a", "href")
+        };
+        let page = CrawlData {
+            url: "https://example.com/".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: Some("text/html; charset=utf-8".to_string()),
+            title: Some("Example Domain".to_string()),
+            size: Some(1256),
+            truncated: false,
+            response_time: Some(Duration::from_micros(84_200)),
+            error: None,
+            links: HashSet::from([elsewhere, about]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        };
+        let unreachable = CrawlData {
+            url: "https://example.com/about".to_string(),
+            parent: Some("https://example.com/".to_string()),
+            redirect: None,
+            depth: 1,
+            status: None,
+            attempts: 3,
+            content_type: None,
+            title: None,
+            size: None,
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: HashSet::new(),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        };
+        snd.send(page).expect("test channel is open");
+        snd.send(unreachable).expect("test channel is open");
+        drop(snd);
+
+        let (mut pages, mut edges) = (vec![], vec![]);
+        csv(rcv, &mut pages, &mut edges)
+            .await
+            .expect("writing to a Vec works");
+
+        assert_eq!(
+            String::from_utf8(pages).expect("CSV is UTF-8"),
+            "\
+url,status,content_type,depth,size,response_time_ms,title
+https://example.com/,200,text/html; charset=utf-8,0,1256,84.2,Example Domain
+https://example.com/about,,,1,,,
+"
+        );
+        assert_eq!(
+            String::from_utf8(edges).expect("CSV is UTF-8"),
+            "\
+source,target,text,rel,internal,excluded
+https://example.com/,https://example.com/about,\"About \"\"us\"\", and more\",,true,false
+https://example.com/,https://example.org/,,nofollow noopener,false,false
+"
+        );
+    }
+
+    #[tokio::test]
+    async fn headers_are_written_without_pages() {
+        let (snd, rcv) = unbounded_channel();
+        drop(snd);
+
+        let (mut pages, mut edges) = (vec![], vec![]);
+        csv(rcv, &mut pages, &mut edges)
+            .await
+            .expect("writing to a Vec works");
+
+        assert_eq!(
+            pages,
+            b"url,status,content_type,depth,size,response_time_ms,title\n"
+        );
+        assert_eq!(edges, b"source,target,text,rel,internal,excluded\n");
+    }
+}
@@ -0,0 +1,840 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+use url::Url;
+
+use crate::{CrawlData, ErrorKind, Link};
+
+/// How to shape the link graph of a crawl.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphOptions {
+    /// Merge pages into one node per path prefix this many segments long, so
+    /// with 1, `/blog/2020/post.html` and `/blog/` are both `/blog/`.
+    pub collapse_segments: Option<usize>,
+    /// Leave out pages more than this many clicks from a seed.
+    pub max_depth: Option<usize>,
+}
+
+/// How a page turned out, for styling its node.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Health {
+    Ok,
+    Redirected,
+    ClientError,
+    ServerError,
+    /// No response was received at all, or its body couldn't be read.
+    Failed,
+}
+
+impl Health {
+    fn of(data: &CrawlData) -> Self {
+        if let Some(error) = &data.error {
+            return match error.kind {
+                ErrorKind::Status(400..=499) => Self::ClientError,
+                ErrorKind::Status(500..=599) => Self::ServerError,
+                _ => Self::Failed,
+            };
+        }
+
+        match data.status {
+            Some(400..=499) => Self::ClientError,
+            Some(500..=599) => Self::ServerError,
+            Some(_) if data.redirect.is_some() => Self::Redirected,
+            Some(_) => Self::Ok,
+            None => Self::Failed,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Redirected => "redirected",
+            Self::ClientError => "client_error",
+            Self::ServerError => "server_error",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Self::Ok => None,
+            Self::Redirected => Some("#e69500"),
+            Self::ClientError => Some("#d00000"),
+            Self::ServerError => Some("#800000"),
+            Self::Failed => Some("#808080"),
+        }
+    }
+}
+
+/// A page, or several collapsed into one.
+#[derive(Debug)]
+struct Node {
+    /// The number of the node, in order of URL.
+    id: usize,
+    depth: usize,
+    /// The worst outcome of any of its pages.
+    health: Health,
+    pages: usize,
+    /// The status, type, title and size of the first of its pages crawled.
+    status: Option<u16>,
+    content_type: Option<String>,
+    title: Option<String>,
+    size: Option<u64>,
+}
+
+impl Node {
+    fn label(&self, url: &str) -> String {
+        match self.pages {
+            1 => url.to_string(),
+            pages => format!("{url} ({pages} pages)"),
+        }
+    }
+
+    /// The values of the node attributes it has, by name.
+    fn attributes(&self, url: &str) -> Vec<(&'static str, String)> {
+        let mut attributes = vec![("url", url.to_string())];
+        attributes.extend(self.status.map(|status| ("status", status.to_string())));
+        attributes.push(("depth", self.depth.to_string()));
+        attributes.extend(
+            self.content_type
+                .clone()
+                .map(|content_type| ("content_type", content_type)),
+        );
+        attributes.extend(self.title.clone().map(|title| ("title", title)));
+        attributes.extend(self.size.map(|size| ("size", size.to_string())));
+        attributes.push(("pages", self.pages.to_string()));
+
+        attributes
+    }
+}
+
+/// The name, GraphML type and GEXF type of every node attribute.
+const NODE_ATTRIBUTES: &[(&str, &str, &str)] = &[
+    ("url", "string", "string"),
+    ("status", "int", "integer"),
+    ("depth", "int", "integer"),
+    ("content_type", "string", "string"),
+    ("title", "string", "string"),
+    ("size", "long", "long"),
+    ("pages", "int", "integer"),
+];
+
+/// The name of every edge attribute, all of which are strings.
+const EDGE_ATTRIBUTES: &[&str] = &["text", "rel", "element", "attribute"];
+
+/// The values of the edge attributes `link` has, by name.
+fn link_attributes(link: &Link) -> Vec<(&'static str, String)> {
+    let mut attributes = vec![];
+    attributes.extend(link.text.clone().map(|text| ("text", text)));
+    if !link.rel.is_empty() {
+        attributes.push(("rel", link.rel.join(" ")));
+    }
+    attributes.push(("element", link.element.clone()));
+    attributes.push(("attribute", link.attribute.clone()));
+
+    attributes
+}
+
+/// The crawled pages and the internal links between them.
+#[derive(Debug)]
+struct Graph {
+    /// Keyed by URL, or path prefix if collapsed.
+    nodes: BTreeMap<String, Node>,
+    /// Every link, keyed by the source and target node keys.
+    edges: BTreeMap<(String, String), Vec<Link>>,
+}
+
+impl Graph {
+    /// Receive every page, then link them up.
+    ///
+    /// Only links between crawled pages are kept, so external links and
+    /// those that weren't followed are left out.
+    async fn receive(
+        mut print_channel: UnboundedReceiver<CrawlData>,
+        options: &GraphOptions,
+    ) -> Self {
+        let mut pages = vec![];
+        while let Some(data) = print_channel.recv().await {
+            debug!("graph writer received crawl data for {}", data.url);
+            pages.push(data);
+        }
+
+        Self::new(&pages, options)
+    }
+
+    fn new(pages: &[CrawlData], options: &GraphOptions) -> Self {
+        let key = |url: &str| collapse(url, options.collapse_segments);
+        let pages: Vec<_> = pages
+            .iter()
+            .filter(|data| options.max_depth.is_none_or(|max| data.depth <= max))
+            .collect();
+
+        let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+        for data in &pages {
+            let health = Health::of(data);
+            nodes
+                .entry(key(&data.url))
+                .and_modify(|node| {
+                    node.depth = node.depth.min(data.depth);
+                    node.health = node.health.max(health);
+                    node.pages += 1;
+                })
+                .or_insert(Node {
+                    id: 0,
+                    depth: data.depth,
+                    health,
+                    pages: 1,
+                    status: data.status,
+                    content_type: data.content_type.clone(),
+                    title: data.title.clone(),
+                    size: data.size,
+                });
+        }
+        for (id, node) in nodes.values_mut().enumerate() {
+            node.id = id;
+        }
+
+        // Links point at where pages were linked, not where they redirected.
+        let mut edges: BTreeMap<_, Vec<Link>> = BTreeMap::new();
+        for data in &pages {
+            let source = key(&data.url);
+            for link in &data.links {
+                let target = key(&link.url);
+                if target != source && nodes.contains_key(&target) {
+                    edges
+                        .entry((source.clone(), target))
+                        .or_default()
+                        .push(link.clone());
+                }
+            }
+        }
+        for links in edges.values_mut() {
+            links.sort_by(|a, b| {
+                (&a.href, &a.element, &a.attribute, &a.text).cmp(&(
+                    &b.href,
+                    &b.element,
+                    &b.attribute,
+                    &b.text,
+                ))
+            });
+        }
+
+        Self { nodes, edges }
+    }
+
+    fn write_dot(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph crawl {{")?;
+        writeln!(out, "  node [shape=box];")?;
+        for (url, node) in &self.nodes {
+            let label = dot_escape(&node.label(url));
+            match node.health.color() {
+                Some(color) => writeln!(
+                    out,
+                    "  n{} [label=\"{label}\", color=\"{color}\", fontcolor=\"{color}\"];",
+                    node.id
+                )?,
+                None => writeln!(out, "  n{} [label=\"{label}\"];", node.id)?,
+            }
+        }
+        for (source, target) in self.edges.keys() {
+            writeln!(
+                out,
+                "  n{} -> n{};",
+                self.nodes[source].id, self.nodes[target].id
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    fn write_mermaid(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "flowchart LR")?;
+        let mut used = BTreeSet::new();
+        for (url, node) in &self.nodes {
+            let label = mermaid_escape(&node.label(url));
+            match node.health {
+                Health::Ok => writeln!(out, "  n{}[\"{label}\"]", node.id)?,
+                health => {
+                    used.insert(health);
+                    writeln!(out, "  n{}[\"{label}\"]:::{}", node.id, health.name())?;
+                }
+            }
+        }
+        for (source, target) in self.edges.keys() {
+            writeln!(
+                out,
+                "  n{} --> n{}",
+                self.nodes[source].id, self.nodes[target].id
+            )?;
+        }
+        for health in used {
+            if let Some(color) = health.color() {
+                writeln!(
+                    out,
+                    "  classDef {} stroke:{color},color:{color}",
+                    health.name()
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every link with its source and target node IDs, one edge per link.
+    fn links(&self) -> impl Iterator<Item = (usize, usize, &Link)> {
+        self.edges.iter().flat_map(|((source, target), links)| {
+            let (source, target) = (self.nodes[source].id, self.nodes[target].id);
+            links.iter().map(move |link| (source, target, link))
+        })
+    }
+
+    fn write_graphml(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (name, graphml_type, _) in NODE_ATTRIBUTES {
+            writeln!(
+                out,
+                r#"  <key id="{name}" for="node" attr.name="{name}" attr.type="{graphml_type}"/>"#
+            )?;
+        }
+        for name in EDGE_ATTRIBUTES {
+            writeln!(
+                out,
+                r#"  <key id="{name}" for="edge" attr.name="{name}" attr.type="string"/>"#
+            )?;
+        }
+        writeln!(out, r#"  <graph id="crawl" edgedefault="directed">"#)?;
+        for (url, node) in &self.nodes {
+            writeln!(out, r#"    <node id="n{}">"#, node.id)?;
+            for (name, value) in node.attributes(url) {
+                writeln!(
+                    out,
+                    r#"      <data key="{name}">{}</data>"#,
+                    xml_escape(&value)
+                )?;
+            }
+            writeln!(out, "    </node>")?;
+        }
+        for (id, (source, target, link)) in self.links().enumerate() {
+            writeln!(
+                out,
+                r#"    <edge id="e{id}" source="n{source}" target="n{target}">"#
+            )?;
+            for (name, value) in link_attributes(link) {
+                writeln!(
+                    out,
+                    r#"      <data key="{name}">{}</data>"#,
+                    xml_escape(&value)
+                )?;
+            }
+            writeln!(out, "    </edge>")?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    fn write_gexf(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#)?;
+        writeln!(out, r#"  <graph defaultedgetype="directed">"#)?;
+        writeln!(out, r#"    <attributes class="node">"#)?;
+        for (name, _, gexf_type) in NODE_ATTRIBUTES {
+            writeln!(
+                out,
+                r#"      <attribute id="{name}" title="{name}" type="{gexf_type}"/>"#
+            )?;
+        }
+        writeln!(out, "    </attributes>")?;
+        writeln!(out, r#"    <attributes class="edge">"#)?;
+        for name in EDGE_ATTRIBUTES {
+            writeln!(
+                out,
+                r#"      <attribute id="{name}" title="{name}" type="string"/>"#
+            )?;
+        }
+        writeln!(out, "    </attributes>")?;
+        writeln!(out, "    <nodes>")?;
+        for (url, node) in &self.nodes {
+            writeln!(
+                out,
+                r#"      <node id="n{}" label="{}">"#,
+                node.id,
+                xml_escape(&node.label(url))
+            )?;
+            write_attvalues(out, node.attributes(url))?;
+            writeln!(out, "      </node>")?;
+        }
+        writeln!(out, "    </nodes>")?;
+        writeln!(out, "    <edges>")?;
+        for (id, (source, target, link)) in self.links().enumerate() {
+            writeln!(
+                out,
+                r#"      <edge id="e{id}" source="n{source}" target="n{target}">"#
+            )?;
+            write_attvalues(out, link_attributes(link))?;
+            writeln!(out, "      </edge>")?;
+        }
+        writeln!(out, "    </edges>")?;
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</gexf>")
+    }
+}
+
+fn write_attvalues(
+    out: &mut impl Write,
+    attributes: Vec<(&'static str, String)>,
+) -> io::Result<()> {
+    writeln!(out, "        <attvalues>")?;
+    for (name, value) in attributes {
+        writeln!(
+            out,
+            r#"          <attvalue for="{name}" value="{}"/>"#,
+            xml_escape(&value)
+        )?;
+    }
+    writeln!(out, "        </attvalues>")
+}
+
+/// Cut the path of `url` down to its first `segments` segments, dropping the
+/// query and fragment, if it's any longer.
+fn collapse(url: &str, segments: Option<usize>) -> String {
+    let (Some(segments), Ok(mut parsed)) = (segments, Url::parse(url)) else {
+        return url.to_string();
+    };
+    let path: Vec<_> = parsed
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if path.len() <= segments && parsed.query().is_none() {
+        return url.to_string();
+    }
+
+    let prefix = path[..segments.min(path.len())].join("/");
+    let prefix = if prefix.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{prefix}/")
+    };
+    if path.len() > segments {
+        parsed.set_path(&prefix);
+    }
+    parsed.set_query(None);
+    parsed.set_fragment(None);
+
+    parsed.to_string()
+}
+
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace aren't allowed in XML.
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Write the link graph of the crawl to `out` in Graphviz DOT, once the
+/// channel closes.
+///
+/// Nodes are coloured by how their page turned out, e.g. red for a 404.
+pub async fn dot(
+    print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+    options: &GraphOptions,
+) -> io::Result<()> {
+    Graph::receive(print_channel, options)
+        .await
+        .write_dot(&mut out)?;
+
+    out.flush()
+}
+
+/// Write the link graph of the crawl to `out` as a Mermaid flowchart, once
+/// the channel closes.
+///
+/// Nodes are coloured by how their page turned out, e.g. red for a 404.
+pub async fn mermaid(
+    print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+    options: &GraphOptions,
+) -> io::Result<()> {
+    Graph::receive(print_channel, options)
+        .await
+        .write_mermaid(&mut out)?;
+
+    out.flush()
+}
+
+/// Write the link graph of the crawl to `out` as GraphML, once the channel
+/// closes.
+///
+/// Nodes have the status, depth, content type, title and size of their page,
+/// and there's an edge for every link, with its text, `rel` and where it was
+/// found.
+pub async fn graphml(
+    print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+    options: &GraphOptions,
+) -> io::Result<()> {
+    Graph::receive(print_channel, options)
+        .await
+        .write_graphml(&mut out)?;
+
+    out.flush()
+}
+
+/// Write the link graph of the crawl to `out` as GEXF, once the channel
+/// closes, with the same attributes as [`graphml`].
+pub async fn gexf(
+    print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+    options: &GraphOptions,
+) -> io::Result<()> {
+    Graph::receive(print_channel, options)
+        .await
+        .write_gexf(&mut out)?;
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FetchError, MetaRobots};
+    use std::collections::HashSet;
+
+    fn page(url: &str, depth: usize, status: Option<u16>, links: &[&str]) -> CrawlData {
+        CrawlData {
+            url: url.to_string(),
+            parent: None,
+            redirect: None,
+            depth,
+            status,
+            attempts: 1,
+            content_type: None,
+            title: None,
+            size: None,
+            truncated: false,
+            response_time: None,
+            error: None,
+            links: links
+                .iter()
+                .map(|link| Link::new(link, "a", "href"))
+                .collect(),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }
+    }
+
+    fn site() -> Vec<CrawlData> {
+        vec![
+            page(
+                "https://example.com/",
+                0,
+                Some(200),
+                &[
+                    "https://example.com/blog/",
+                    "https://example.com/missing",
+                    "https://example.org/",
+                ],
+            ),
+            page(
+                "https://example.com/blog/",
+                1,
+                Some(200),
+                &["https://example.com/blog/2020/post.html"],
+            ),
+            page(
+                "https://example.com/blog/2020/post.html",
+                2,
+                Some(200),
+                &["https://example.com/"],
+            ),
+            page("https://example.com/missing", 1, Some(404), &[]),
+        ]
+    }
+
+    #[test]
+    fn dot_output() {
+        let graph = Graph::new(&site(), &GraphOptions::default());
+
+        let mut out = vec![];
+        graph.write_dot(&mut out).expect("writing to a Vec works");
+
+        assert_eq!(
+            String::from_utf8(out).expect("DOT is UTF-8"),
+            r##"digraph crawl {
+  node [shape=box];
+  n0 [label="https://example.com/"];
+  n1 [label="https://example.com/blog/"];
+  n2 [label="https://example.com/blog/2020/post.html"];
+  n3 [label="https://example.com/missing", color="#d00000", fontcolor="#d00000"];
+  n0 -> n1;
+  n0 -> n3;
+  n1 -> n2;
+  n2 -> n0;
+}
+"##
+        );
+    }
+
+    #[test]
+    fn mermaid_output() {
+        let graph = Graph::new(&site(), &GraphOptions::default());
+
+        let mut out = vec![];
+        graph
+            .write_mermaid(&mut out)
+            .expect("writing to a Vec works");
+
+        assert_eq!(
+            String::from_utf8(out).expect("Mermaid is UTF-8"),
+            r#"flowchart LR
+  n0["https://example.com/"]
+  n1["https://example.com/blog/"]
+  n2["https://example.com/blog/2020/post.html"]
+  n3["https://example.com/missing"]:::client_error
+  n0 --> n1
+  n0 --> n3
+  n1 --> n2
+  n2 --> n0
+  classDef client_error stroke:#d00000,color:#d00000
+"#
+        );
+    }
+
+    /// A page titled `Home & "Away"` with a `nofollow` link to a missing
+    /// page.
+    fn small_site() -> Vec<CrawlData> {
+        let mut home = page(
+            "https://example.com/",
+            0,
+            Some(200),
+            &["https://example.com/missing"],
+        );
+        home.title = Some(r#"Home & "Away""#.to_string());
+        home.links = HashSet::from([Link {
+            text: Some("Missing <page>".to_string()),
+            rel: vec!["nofollow".to_string()],
+            ..Link::new("https://example.com/missing", "a", "href")
+        }]);
+
+        vec![home, page("https://example.com/missing", 1, Some(404), &[])]
+    }
+
+    #[test]
+    fn graphml_output() {
+        let graph = Graph::new(&small_site(), &GraphOptions::default());
+
+        let mut out = vec![];
+        graph
+            .write_graphml(&mut out)
+            .expect("writing to a Vec works");
+
+        assert_eq!(
+            String::from_utf8(out).expect("GraphML is UTF-8"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="url" for="node" attr.name="url" attr.type="string"/>
+  <key id="status" for="node" attr.name="status" attr.type="int"/>
+  <key id="depth" for="node" attr.name="depth" attr.type="int"/>
+  <key id="content_type" for="node" attr.name="content_type" attr.type="string"/>
+  <key id="title" for="node" attr.name="title" attr.type="string"/>
+  <key id="size" for="node" attr.name="size" attr.type="long"/>
+  <key id="pages" for="node" attr.name="pages" attr.type="int"/>
+  <key id="text" for="edge" attr.name="text" attr.type="string"/>
+  <key id="rel" for="edge" attr.name="rel" attr.type="string"/>
+  <key id="element" for="edge" attr.name="element" attr.type="string"/>
+  <key id="attribute" for="edge" attr.name="attribute" attr.type="string"/>
+  <graph id="crawl" edgedefault="directed">
+    <node id="n0">
+      <data key="url">https://example.com/</data>
+      <data key="status">200</data>
+      <data key="depth">0</data>
+      <data key="title">Home &amp; &quot;Away&quot;</data>
+      <data key="pages">1</data>
+    </node>
+    <node id="n1">
+      <data key="url">https://example.com/missing</data>
+      <data key="status">404</data>
+      <data key="depth">1</data>
+      <data key="pages">1</data>
+    </node>
+    <edge id="e0" source="n0" target="n1">
+      <data key="text">Missing &lt;page&gt;</data>
+      <data key="rel">nofollow</data>
+      <data key="element">a</data>
+      <data key="attribute">href</data>
+    </edge>
+  </graph>
+</graphml>
+"#
+        );
+    }
+
+    #[test]
+    fn gexf_output() {
+        let graph = Graph::new(&small_site(), &GraphOptions::default());
+
+        let mut out = vec![];
+        graph.write_gexf(&mut out).expect("writing to a Vec works");
+
+        let out = String::from_utf8(out).expect("GEXF is UTF-8");
+        assert!(out.starts_with(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#,
+        )));
+        assert!(out.contains(
+            r#"      <node id="n0" label="https://example.com/">
+        <attvalues>
+          <attvalue for="url" value="https://example.com/"/>
+          <attvalue for="status" value="200"/>
+          <attvalue for="depth" value="0"/>
+          <attvalue for="title" value="Home &amp; &quot;Away&quot;"/>
+          <attvalue for="pages" value="1"/>
+        </attvalues>
+      </node>"#
+        ));
+        assert!(out.contains(
+            r#"      <edge id="e0" source="n0" target="n1">
+        <attvalues>
+          <attvalue for="text" value="Missing &lt;page&gt;"/>
+          <attvalue for="rel" value="nofollow"/>
+          <attvalue for="element" value="a"/>
+          <attvalue for="attribute" value="href"/>
+        </attvalues>
+      </edge>"#
+        ));
+    }
+
+    #[test]
+    fn xml_is_escaped() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">Tom's & Jerry's\u{7}</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom&apos;s &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn pages_can_be_collapsed_by_prefix() {
+        let options = GraphOptions {
+            collapse_segments: Some(1),
+            ..GraphOptions::default()
+        };
+
+        let graph = Graph::new(&site(), &options);
+
+        assert_eq!(
+            graph.nodes.keys().collect::<Vec<_>>(),
+            [
+                "https://example.com/",
+                "https://example.com/blog/",
+                "https://example.com/missing"
+            ]
+        );
+        assert_eq!(graph.nodes["https://example.com/blog/"].pages, 2);
+        assert!(graph.edges.contains_key(&(
+            "https://example.com/blog/".to_string(),
+            "https://example.com/".to_string()
+        )));
+    }
+
+    #[test]
+    fn graph_can_be_limited_to_a_depth() {
+        let options = GraphOptions {
+            max_depth: Some(1),
+            ..GraphOptions::default()
+        };
+
+        let graph = Graph::new(&site(), &options);
+
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(
+            graph.edges.into_keys().collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                (
+                    "https://example.com/".to_string(),
+                    "https://example.com/blog/".to_string()
+                ),
+                (
+                    "https://example.com/".to_string(),
+                    "https://example.com/missing".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn pages_whose_bodies_failed_are_not_healthy() {
+        let timed_out = CrawlData {
+            error: Some(FetchError {
+                kind: ErrorKind::Timeout,
+                message: "timed out reading body".to_string(),
+            }),
+            ..page("https://example.com/slow", 0, Some(200), &[])
+        };
+        let too_large = CrawlData {
+            error: Some(FetchError {
+                kind: ErrorKind::TooLarge,
+                message: "body larger than 5 bytes".to_string(),
+            }),
+            ..page("https://example.com/large", 0, Some(200), &[])
+        };
+
+        assert_eq!(Health::of(&timed_out), Health::Failed);
+        assert_eq!(Health::of(&too_large), Health::Failed);
+        assert_eq!(
+            Health::of(&page("https://example.com/", 0, Some(200), &[])),
+            Health::Ok
+        );
+    }
+
+    #[test]
+    fn collapse_urls() {
+        assert_eq!(
+            collapse("https://example.com/a/b/c.html?x=1", Some(1)),
+            "https://example.com/a/"
+        );
+        assert_eq!(
+            collapse("https://example.com/a/b/c.html", Some(0)),
+            "https://example.com/"
+        );
+        assert_eq!(
+            collapse("https://example.com/a.html?x=1", Some(1)),
+            "https://example.com/a.html"
+        );
+        assert_eq!(
+            collapse("https://example.com/a/b/", Some(2)),
+            "https://example.com/a/b/"
+        );
+        assert_eq!(
+            collapse("https://example.com/a/b/", None),
+            "https://example.com/a/b/"
+        );
+    }
+}
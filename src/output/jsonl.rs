@@ -0,0 +1,250 @@
+//! One JSON object per line for each crawled page, with this schema:
+//!
+//! ```json
+//! {
+//!   "url": "https://example.com/",
+//!   "redirect": null,
+//!   "parent": null,
+//!   "depth": 0,
+//!   "status": 200,
+//!   "attempts": 1,
+//!   "content_type": "text/html; charset=utf-8",
+//!   "title": "Example Domain",
+//!   "size": 1256,
+//!   "truncated": false,
+//!   "response_time_ms": 84.2,
+//!   "noindex": false,
+//!   "nofollow": false,
+//!   "error": null,
+//!   "links": [
+//!     {
+//!       "url": "https://example.com/about",
+//!       "href": "/about",
+//!       "element": "a",
+//!       "attribute": "href",
+//!       "text": "About us",
+//!       "title": null,
+//!       "rel": [],
+//!       "internal": true,
+//!       "excluded": false
+//!     }
+//!   ],
+//!   "non_http_links": []
+//! }
+//! ```
+//!
+//! `error`, when there is one, is an object with a `kind` of `status`,
+//! `timeout`, `network` or `too_large` and a `message`. Links are sorted by
+//! URL. New fields may be added, but existing ones won't change meaning.
+
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+
+use super::error_kind;
+use crate::{CrawlData, FetchError, Link};
+
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    url: &'a str,
+    redirect: Option<&'a str>,
+    parent: Option<&'a str>,
+    depth: usize,
+    status: Option<u16>,
+    attempts: u32,
+    content_type: Option<&'a str>,
+    title: Option<&'a str>,
+    size: Option<u64>,
+    truncated: bool,
+    response_time_ms: Option<f64>,
+    noindex: bool,
+    nofollow: bool,
+    error: Option<ErrorRecord<'a>>,
+    links: Vec<LinkRecord<'a>>,
+    non_http_links: Vec<LinkRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord<'a> {
+    kind: &'static str,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct LinkRecord<'a> {
+    url: &'a str,
+    href: &'a str,
+    element: &'a str,
+    attribute: &'a str,
+    text: Option<&'a str>,
+    title: Option<&'a str>,
+    rel: &'a [String],
+    internal: bool,
+    excluded: bool,
+}
+
+impl<'a> From<&'a CrawlData> for PageRecord<'a> {
+    fn from(data: &'a CrawlData) -> Self {
+        Self {
+            url: &data.url,
+            redirect: data.redirect.as_deref(),
+            parent: data.parent.as_deref(),
+            depth: data.depth,
+            status: data.status,
+            attempts: data.attempts,
+            content_type: data.content_type.as_deref(),
+            title: data.title.as_deref(),
+            size: data.size,
+            truncated: data.truncated,
+            response_time_ms: data
+                .response_time
+                .map(|response_time| response_time.as_secs_f64() * 1000.0),
+            noindex: data.meta_robots.noindex,
+            nofollow: data.meta_robots.nofollow,
+            error: data.error.as_ref().map(ErrorRecord::from),
+            links: link_records(&data.links),
+            non_http_links: link_records(&data.non_http_links),
+        }
+    }
+}
+
+impl<'a> From<&'a FetchError> for ErrorRecord<'a> {
+    fn from(error: &'a FetchError) -> Self {
+        Self {
+            kind: error_kind(&error.kind),
+            message: &error.message,
+        }
+    }
+}
+
+impl<'a> From<&'a Link> for LinkRecord<'a> {
+    fn from(link: &'a Link) -> Self {
+        Self {
+            url: &link.url,
+            href: &link.href,
+            element: &link.element,
+            attribute: &link.attribute,
+            text: link.text.as_deref(),
+            title: link.title.as_deref(),
+            rel: &link.rel,
+            internal: link.internal,
+            excluded: link.excluded,
+        }
+    }
+}
+
+/// The links as records, sorted so the output is the same from run to run.
+fn link_records(links: &HashSet<Link>) -> Vec<LinkRecord<'_>> {
+    let mut records: Vec<_> = links.iter().map(LinkRecord::from).collect();
+    records.sort_by_key(|link| (link.url, link.href, link.element, link.attribute, link.text));
+
+    records
+}
+
+/// Write each page to `out` as a line of JSON as they arrive on the channel.
+pub async fn json_lines(
+    mut print_channel: UnboundedReceiver<CrawlData>,
+    mut out: impl Write,
+) -> io::Result<()> {
+    while let Some(data) = print_channel.recv().await {
+        debug!("JSON Lines writer received crawl data for {}", data.url);
+
+        serde_json::to_writer(&mut out, &PageRecord::from(&data))?;
+        writeln!(out)?;
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, MetaRobots};
+    use std::time::Duration;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[tokio::test]
+    async fn pages_are_written_one_per_line() {
+        let (snd, rcv) = unbounded_channel();
+        let link = Link {
+            url: "https://example.com/about".to_string(),
+            text: Some("About us".to_string()),
+            internal: true,
+            ..Link::new("/about", "a", "href")
+        };
+        let page = CrawlData {
+            url: "https://example.com/".to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: Some("text/html".to_string()),
+            title: None,
+            size: Some(1256),
+            truncated: false,
+            response_time: Some(Duration::from_millis(84)),
+            error: None,
+            links: HashSet::from([link]),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        };
+        let missing = CrawlData {
+            url: "https://example.com/missing".to_string(),
+            parent: Some("https://example.com/".to_string()),
+            redirect: None,
+            depth: 1,
+            status: Some(404),
+            attempts: 1,
+            content_type: Some("text/html".to_string()),
+            title: None,
+            size: None,
+            truncated: false,
+            response_time: Some(Duration::from_millis(84)),
+            error: Some(FetchError {
+                kind: ErrorKind::Status(404),
+                message: "not found".to_string(),
+            }),
+            links: HashSet::new(),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        };
+        snd.send(page).expect("test channel is open");
+        snd.send(missing).expect("test channel is open");
+        drop(snd);
+
+        let mut out = vec![];
+        json_lines(rcv, &mut out)
+            .await
+            .expect("writing to a Vec works");
+
+        let out = String::from_utf8(out).expect("JSON is UTF-8");
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                concat!(
+                    r#"{"url":"https://example.com/","redirect":null,"parent":null,"depth":0,"#,
+                    r#""status":200,"attempts":1,"content_type":"text/html","title":null,"size":1256,"#,
+                    r#""truncated":false,"response_time_ms":84.0,"noindex":false,"#,
+                    r#""nofollow":false,"error":null,"links":[{"url":"https://example.com/about","#,
+                    r#""href":"/about","element":"a","attribute":"href","text":"About us","#,
+                    r#""title":null,"rel":[],"internal":true,"excluded":false}],"non_http_links":[]}"#,
+                ),
+                concat!(
+                    r#"{"url":"https://example.com/missing","redirect":null,"#,
+                    r#""parent":"https://example.com/","depth":1,"status":404,"attempts":1,"#,
+                    r#""content_type":"text/html","title":null,"size":null,"truncated":false,"#,
+                    r#""response_time_ms":84.0,"noindex":false,"nofollow":false,"#,
+                    r#""error":{"kind":"status","message":"not found"},"links":[],"#,
+                    r#""non_http_links":[]}"#,
+                ),
+            ]
+        );
+    }
+}
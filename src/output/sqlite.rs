@@ -0,0 +1,315 @@
+//! A SQLite database of crawls, written a page at a time so that what was
+//! crawled survives the crawler crashing.
+//!
+//! Each crawl is a row in `runs`, and every other row has the `run_id` of the
+//! crawl it's from, so one database can hold many crawls. The schema is
+//! created if it doesn't exist yet:
+//!
+#![doc = concat!("```sql\n", include_str!("sqlite.sql"), "```")]
+//!
+//! Booleans are 0 or 1 and missing values are NULL. For example, to find the
+//! pages linking to broken ones in the latest crawl:
+//!
+//! ```sql
+//! SELECT links.source, pages.url, pages.status
+//! FROM pages JOIN links ON links.run_id = pages.run_id AND links.target = pages.url
+//! WHERE pages.run_id = (SELECT max(id) FROM runs) AND pages.status >= 400;
+//! ```
+
+use rusqlite::{params, Connection, Transaction};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
+
+use super::error_kind;
+use crate::CrawlData;
+
+/// Record a new crawl in `db` and write each page to it as they arrive on the
+/// channel, returning the crawl's run ID.
+pub async fn sqlite(
+    mut print_channel: UnboundedReceiver<CrawlData>,
+    db: &mut Connection,
+) -> rusqlite::Result<i64> {
+    db.execute_batch(include_str!("sqlite.sql"))?;
+    db.execute("INSERT INTO runs DEFAULT VALUES", [])?;
+    let run_id = db.last_insert_rowid();
+
+    while let Some(data) = print_channel.recv().await {
+        debug!("SQLite writer received crawl data for {}", data.url);
+
+        // Each page is committed with its links, so none are half written.
+        let tx = db.transaction()?;
+        insert_page(&tx, run_id, &data)?;
+        tx.commit()?;
+    }
+
+    db.execute(
+        "UPDATE runs SET finished_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now') WHERE id = ?1",
+        [run_id],
+    )?;
+
+    Ok(run_id)
+}
+
+fn insert_page(tx: &Transaction, run_id: i64, data: &CrawlData) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO pages (run_id, url, parent, depth, status, attempts, content_type, title,
+            size, truncated, response_time_ms, noindex, nofollow)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            run_id,
+            data.url,
+            data.parent,
+            data.depth,
+            data.status,
+            data.attempts,
+            data.content_type,
+            data.title,
+            data.size,
+            data.truncated,
+            data.response_time
+                .map(|response_time| response_time.as_secs_f64() * 1000.0),
+            data.meta_robots.noindex,
+            data.meta_robots.nofollow,
+        ],
+    )?;
+
+    let mut insert_link = tx.prepare_cached(
+        "INSERT INTO links (run_id, source, target, href, element, attribute, text, title, rel,
+            internal, excluded)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    for link in &data.links {
+        insert_link.execute(params![
+            run_id,
+            data.url,
+            link.url,
+            link.href,
+            link.element,
+            link.attribute,
+            link.text,
+            link.title,
+            link.rel.join(" "),
+            link.internal,
+            link.excluded,
+        ])?;
+    }
+
+    if let Some(redirect) = &data.redirect {
+        tx.execute(
+            "INSERT INTO redirects (run_id, url, target) VALUES (?1, ?2, ?3)",
+            params![run_id, data.url, redirect],
+        )?;
+    }
+    if let Some(error) = &data.error {
+        tx.execute(
+            "INSERT INTO errors (run_id, url, kind, message) VALUES (?1, ?2, ?3, ?4)",
+            params![run_id, data.url, error_kind(&error.kind), error.message],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, FetchError, Link, MetaRobots};
+    use std::{collections::HashSet, time::Duration};
+    use tokio::sync::mpsc::unbounded_channel;
+
+    /// A page's URL, parent, status and title.
+    type PageRow = (String, Option<String>, Option<u16>, Option<String>);
+
+    fn page(url: &str, links: &[&str]) -> CrawlData {
+        CrawlData {
+            url: url.to_string(),
+            parent: None,
+            redirect: None,
+            depth: 0,
+            status: Some(200),
+            attempts: 1,
+            content_type: Some("text/html".to_string()),
+            title: None,
+            size: Some(1256),
+            truncated: false,
+            response_time: Some(Duration::from_millis(84)),
+            error: None,
+            links: links
+                .iter()
+                .map(|url| Link {
+                    rel: vec!["nofollow".to_string(), "noopener".to_string()],
+                    internal: true,
+                    ..Link::new(url, "a", "href")
+                })
+                .collect(),
+            non_http_links: HashSet::new(),
+            meta_robots: MetaRobots::default(),
+        }
+    }
+
+    async fn crawl(db: &mut Connection, pages: Vec<CrawlData>) -> i64 {
+        let (snd, rcv) = unbounded_channel();
+        for page in pages {
+            snd.send(page).expect("test channel is open");
+        }
+        drop(snd);
+
+        sqlite(rcv, db)
+            .await
+            .expect("test database should be written")
+    }
+
+    #[tokio::test]
+    async fn pages_links_redirects_and_errors_are_written() {
+        let mut db = Connection::open_in_memory().expect("test database should open");
+        let home = CrawlData {
+            title: Some("Home".to_string()),
+            ..page("https://example.com/", &["https://example.com/old"])
+        };
+        let old = CrawlData {
+            parent: Some("https://example.com/".to_string()),
+            redirect: Some("https://example.com/missing".to_string()),
+            depth: 1,
+            status: Some(404),
+            error: Some(FetchError {
+                kind: ErrorKind::Status(404),
+                message: "not found".to_string(),
+            }),
+            ..page("https://example.com/old", &[])
+        };
+
+        let run_id = crawl(&mut db, vec![home, old]).await;
+
+        let pages: Vec<PageRow> = db
+            .prepare("SELECT url, parent, status, title FROM pages ORDER BY url")
+            .and_then(|mut pages| {
+                pages
+                    .query_map([], |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                    })?
+                    .collect()
+            })
+            .expect("test query should succeed");
+        assert_eq!(
+            pages,
+            [
+                (
+                    "https://example.com/".to_string(),
+                    None,
+                    Some(200),
+                    Some("Home".to_string())
+                ),
+                (
+                    "https://example.com/old".to_string(),
+                    Some("https://example.com/".to_string()),
+                    Some(404),
+                    None
+                ),
+            ]
+        );
+
+        let link: (i64, String, String, String, bool) = db
+            .query_row(
+                "SELECT run_id, source, target, rel, internal FROM links",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .expect("test query should succeed");
+        assert_eq!(
+            link,
+            (
+                run_id,
+                "https://example.com/".to_string(),
+                "https://example.com/old".to_string(),
+                "nofollow noopener".to_string(),
+                true
+            )
+        );
+
+        let redirect: (String, String) = db
+            .query_row("SELECT url, target FROM redirects", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .expect("test query should succeed");
+        assert_eq!(
+            redirect,
+            (
+                "https://example.com/old".to_string(),
+                "https://example.com/missing".to_string()
+            )
+        );
+
+        let error: (String, String, String) = db
+            .query_row("SELECT url, kind, message FROM errors", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .expect("test query should succeed");
+        assert_eq!(
+            error,
+            (
+                "https://example.com/old".to_string(),
+                "status".to_string(),
+                "not found".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_are_kept_apart() {
+        let mut db = Connection::open_in_memory().expect("test database should open");
+
+        let first = crawl(&mut db, vec![page("https://example.com/", &[])]).await;
+        let second = crawl(&mut db, vec![page("https://example.com/", &[])]).await;
+
+        assert_ne!(first, second);
+        let pages: i64 = db
+            .query_row(
+                "SELECT count(*) FROM pages WHERE run_id = ?1",
+                [second],
+                |row| row.get(0),
+            )
+            .expect("test query should succeed");
+        assert_eq!(pages, 1);
+        let unfinished: i64 = db
+            .query_row(
+                "SELECT count(*) FROM runs WHERE finished_at IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .expect("test query should succeed");
+        assert_eq!(unfinished, 0);
+    }
+
+    #[tokio::test]
+    async fn pages_are_committed_as_they_arrive() {
+        let mut db = Connection::open_in_memory().expect("test database should open");
+        let (snd, rcv) = unbounded_channel();
+        snd.send(page("https://example.com/", &["https://example.com/a"]))
+            .expect("test channel is open");
+
+        // The writer is still waiting for more pages when it's dropped, like
+        // when the crawler crashes.
+        let writer = sqlite(rcv, &mut db);
+        let timed_out = tokio::time::timeout(Duration::from_millis(100), writer).await;
+        assert!(timed_out.is_err());
+
+        let (pages, links, unfinished): (i64, i64, i64) = db
+            .query_row(
+                "SELECT (SELECT count(*) FROM pages), (SELECT count(*) FROM links),
+                    (SELECT count(*) FROM runs WHERE finished_at IS NULL)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .expect("test query should succeed");
+        assert_eq!((pages, links, unfinished), (1, 1, 1));
+        drop(snd);
+    }
+}
@@ -0,0 +1,73 @@
+-- One row per crawl, whose id keys the rows of every other table.
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    -- UTC, like 2024-01-31T09:30:00Z.
+    started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    -- NULL until the crawl finishes, so also if it crashed.
+    finished_at TEXT
+);
+
+-- One row per crawled page, whether or not it could be fetched.
+CREATE TABLE IF NOT EXISTS pages (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    url TEXT NOT NULL,
+    -- The page the URL was first found on, or NULL for a seed.
+    parent TEXT,
+    depth INTEGER NOT NULL,
+    status INTEGER,
+    attempts INTEGER NOT NULL,
+    content_type TEXT,
+    title TEXT,
+    -- In bytes.
+    size INTEGER,
+    truncated INTEGER NOT NULL,
+    response_time_ms REAL,
+    noindex INTEGER NOT NULL,
+    nofollow INTEGER NOT NULL,
+    PRIMARY KEY (run_id, url)
+);
+CREATE INDEX IF NOT EXISTS pages_by_status ON pages (run_id, status);
+
+-- One row per HTTP(S) link found on a page.
+CREATE TABLE IF NOT EXISTS links (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    source TEXT NOT NULL,
+    -- The resolved URL and the attribute value it was resolved from.
+    target TEXT NOT NULL,
+    href TEXT NOT NULL,
+    element TEXT NOT NULL,
+    attribute TEXT NOT NULL,
+    text TEXT,
+    title TEXT,
+    -- Space separated, like nofollow noopener.
+    rel TEXT NOT NULL,
+    internal INTEGER NOT NULL,
+    -- Internal, but not followed because of the include and exclude rules.
+    excluded INTEGER NOT NULL,
+    FOREIGN KEY (run_id, source) REFERENCES pages (run_id, url)
+);
+CREATE INDEX IF NOT EXISTS links_by_source ON links (run_id, source);
+CREATE INDEX IF NOT EXISTS links_by_target ON links (run_id, target);
+
+-- One row per page that was redirected.
+CREATE TABLE IF NOT EXISTS redirects (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    url TEXT NOT NULL,
+    -- Where the page was finally fetched from.
+    target TEXT NOT NULL,
+    PRIMARY KEY (run_id, url),
+    FOREIGN KEY (run_id, url) REFERENCES pages (run_id, url)
+);
+CREATE INDEX IF NOT EXISTS redirects_by_target ON redirects (run_id, target);
+
+-- One row per page that couldn't be fetched.
+CREATE TABLE IF NOT EXISTS errors (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    url TEXT NOT NULL,
+    -- status, timeout, network or too_large.
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    PRIMARY KEY (run_id, url),
+    FOREIGN KEY (run_id, url) REFERENCES pages (run_id, url)
+);
+CREATE INDEX IF NOT EXISTS errors_by_kind ON errors (run_id, kind);
@@ -0,0 +1,252 @@
+use anyhow::{anyhow, Error, Result};
+use regex::Regex;
+use std::{fmt, str::FromStr};
+use url::Url;
+
+/// A pattern for the URLs a [`UrlRule`] applies to.
+///
+/// Patterns can be parsed from strings like `/docs/`, `prefix:/docs/`,
+/// `glob:/docs/**/*.html` and `regex:\.pdf$`, where a pattern without a
+/// kind is a path prefix.
+#[derive(Clone, Debug)]
+pub enum UrlPattern {
+    /// URLs whose path starts with this.
+    Prefix(String),
+    /// URLs whose path matches this glob, where `*` and `?` don't match `/`
+    /// but `**` does, and `**/` matches no directories too.
+    Glob(String),
+    /// URLs matching this regex anywhere in the full URL.
+    Regex(Regex),
+}
+
+impl UrlPattern {
+    fn matches(&self, url: &Url, glob: Option<&Regex>) -> bool {
+        match self {
+            Self::Prefix(prefix) => url.path().starts_with(prefix.as_str()),
+            Self::Glob(_) => glob.is_some_and(|glob| glob.is_match(url.path())),
+            Self::Regex(regex) => regex.is_match(url.as_str()),
+        }
+    }
+}
+
+/// Translate a path glob into an anchored regex.
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` is any number of directories, including none.
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex).expect("every other character is escaped")
+}
+
+impl PartialEq for UrlPattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Prefix(a), Self::Prefix(b)) | (Self::Glob(a), Self::Glob(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for UrlPattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        if let Some(glob) = pattern.strip_prefix("glob:") {
+            Ok(Self::Glob(glob.to_string()))
+        } else if let Some(regex) = pattern.strip_prefix("regex:") {
+            Regex::new(regex)
+                .map(Self::Regex)
+                .map_err(|error| anyhow!("Invalid regex {regex:?} ({error})"))
+        } else {
+            let prefix = pattern.strip_prefix("prefix:").unwrap_or(pattern);
+
+            Ok(Self::Prefix(prefix.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for UrlPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefix(prefix) => write!(f, "prefix:{prefix}"),
+            Self::Glob(glob) => write!(f, "glob:{glob}"),
+            Self::Regex(regex) => write!(f, "regex:{regex}"),
+        }
+    }
+}
+
+/// Whether to crawl URLs matching a pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UrlRule {
+    Include(UrlPattern),
+    Exclude(UrlPattern),
+}
+
+impl UrlRule {
+    fn pattern(&self) -> &UrlPattern {
+        match self {
+            Self::Include(pattern) | Self::Exclude(pattern) => pattern,
+        }
+    }
+}
+
+/// An ordered list of [`UrlRule`]s, where the first matching rule wins.
+///
+/// URLs matching no rule are included, unless there are include rules, in
+/// which case they are excluded.
+#[derive(Debug, Default)]
+pub(crate) struct UrlRules {
+    rules: Vec<UrlRule>,
+    /// The compiled regex for each glob rule, by index.
+    globs: Vec<Option<Regex>>,
+}
+
+impl UrlRules {
+    pub(crate) fn new(rules: Vec<UrlRule>) -> Self {
+        let globs = rules
+            .iter()
+            .map(|rule| match rule.pattern() {
+                UrlPattern::Glob(glob) => Some(glob_to_regex(glob)),
+                _ => None,
+            })
+            .collect();
+
+        Self { rules, globs }
+    }
+
+    pub(crate) fn rules(&self) -> &[UrlRule] {
+        &self.rules
+    }
+
+    /// Whether `url` should be crawled according to the rules.
+    pub(crate) fn includes(&self, url: &Url) -> bool {
+        let first_match = self
+            .rules
+            .iter()
+            .zip(&self.globs)
+            .find(|(rule, glob)| rule.pattern().matches(url, glob.as_ref()));
+
+        match first_match {
+            Some((rule, _)) => matches!(rule, UrlRule::Include(_)),
+            None => !self
+                .rules
+                .iter()
+                .any(|rule| matches!(rule, UrlRule::Include(_))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).expect("test URL should parse")
+    }
+
+    fn pattern(pattern: &str) -> UrlPattern {
+        pattern.parse().expect("test pattern should parse")
+    }
+
+    fn rules(rules: Vec<UrlRule>) -> UrlRules {
+        UrlRules::new(rules)
+    }
+
+    #[test]
+    fn parse_patterns() {
+        assert_eq!(pattern("/docs/"), UrlPattern::Prefix("/docs/".to_string()));
+        assert_eq!(
+            pattern("prefix:/docs/"),
+            UrlPattern::Prefix("/docs/".to_string())
+        );
+        assert_eq!(
+            pattern("glob:/docs/*.html"),
+            UrlPattern::Glob("/docs/*.html".to_string())
+        );
+        assert_eq!(
+            pattern(r"regex:\.pdf$"),
+            UrlPattern::Regex(Regex::new(r"\.pdf$").expect("test regex should compile"))
+        );
+        assert!("regex:(".parse::<UrlPattern>().is_err());
+    }
+
+    #[test]
+    fn no_rules_include_everything() {
+        assert!(rules(vec![]).includes(&url("https://example.com/anything")));
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let rules = rules(vec![
+            UrlRule::Exclude(pattern("/docs/archive/")),
+            UrlRule::Include(pattern("/docs/")),
+        ]);
+
+        assert!(rules.includes(&url("https://example.com/docs/intro.html")));
+        assert!(!rules.includes(&url("https://example.com/docs/archive/2019.html")));
+        assert!(!rules.includes(&url("https://example.com/blog/")));
+    }
+
+    #[test]
+    fn unmatched_urls_are_included_without_include_rules() {
+        let rules = rules(vec![UrlRule::Exclude(pattern(r"regex:\.pdf$"))]);
+
+        assert!(rules.includes(&url("https://example.com/docs/intro.html")));
+        assert!(!rules.includes(&url("https://example.com/docs/manual.pdf")));
+    }
+
+    #[test]
+    fn globs_match_paths() {
+        let rules = rules(vec![UrlRule::Include(pattern("glob:/docs/*.html"))]);
+
+        assert!(rules.includes(&url("https://example.com/docs/intro.html")));
+        assert!(rules.includes(&url("https://example.com/docs/intro.html?lang=en")));
+        assert!(!rules.includes(&url("https://example.com/docs/api/intro.html")));
+        assert!(!rules.includes(&url("https://example.com/docs/intro.htm")));
+    }
+
+    #[test]
+    fn double_star_globs_cross_directories() {
+        let rules = rules(vec![UrlRule::Include(pattern("glob:/docs/**.html"))]);
+
+        assert!(rules.includes(&url("https://example.com/docs/api/v2/intro.html")));
+        assert!(!rules.includes(&url("https://example.com/blog/intro.html")));
+    }
+
+    #[test]
+    fn double_star_slash_matches_no_directories_too() {
+        let rules = rules(vec![UrlRule::Include(pattern("glob:/docs/**/*.html"))]);
+
+        assert!(rules.includes(&url("https://example.com/docs/intro.html")));
+        assert!(rules.includes(&url("https://example.com/docs/api/v2/intro.html")));
+        assert!(!rules.includes(&url("https://example.com/docsintro.html")));
+        assert!(!rules.includes(&url("https://example.com/blog/intro.html")));
+    }
+
+    #[test]
+    fn glob_metacharacters_are_literal() {
+        let rules = rules(vec![UrlRule::Include(pattern("glob:/a.b/*"))]);
+
+        assert!(rules.includes(&url("https://example.com/a.b/c")));
+        assert!(!rules.includes(&url("https://example.com/axb/c")));
+    }
+}
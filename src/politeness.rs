@@ -0,0 +1,286 @@
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time,
+};
+use tracing::{trace, warn};
+use url::Url;
+
+/// How long to back off from an overloaded origin that didn't say how long.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest we'll back off from an origin that didn't say how long.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The longest we'll wait between requests to an origin, however long its
+/// `Retry-After` or crawl delay asks for, so one origin can't stall the crawl.
+const MAX_WAIT: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+struct Schedule {
+    next_fetch: Instant,
+    /// How long we last backed off for, reset by a successful response.
+    backoff: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Origin {
+    connections: Arc<Semaphore>,
+    schedule: Mutex<Schedule>,
+}
+
+/// Per-origin rate limits and connection caps, shared by every worker.
+#[derive(Debug)]
+pub(crate) struct Politeness {
+    interval: Duration,
+    max_connections: usize,
+    origins: Mutex<HashMap<String, Arc<Origin>>>,
+}
+
+impl Politeness {
+    /// Allow `requests_per_second` and `max_connections` to each origin, if set.
+    pub(crate) fn new(requests_per_second: Option<f64>, max_connections: Option<usize>) -> Self {
+        let interval = requests_per_second
+            .and_then(|rps| Duration::try_from_secs_f64(rps.recip()).ok())
+            .unwrap_or_default();
+
+        Self {
+            interval,
+            max_connections: max_connections.unwrap_or(Semaphore::MAX_PERMITS),
+            origins: Mutex::default(),
+        }
+    }
+
+    fn origin(&self, url: &Url) -> Arc<Origin> {
+        let key = url.origin().ascii_serialization();
+
+        self.origins
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| {
+                Arc::new(Origin {
+                    connections: Arc::new(Semaphore::new(self.max_connections)),
+                    schedule: Mutex::new(Schedule {
+                        next_fetch: Instant::now(),
+                        backoff: None,
+                    }),
+                })
+            })
+            .clone()
+    }
+
+    /// Wait for a free connection and our turn to request `url`.
+    ///
+    /// Turns are spaced by the rate limit or `crawl_delay`, whichever is
+    /// longer. The connection is released when the permit is dropped.
+    pub(crate) async fn acquire(
+        &self,
+        url: &Url,
+        crawl_delay: Option<Duration>,
+    ) -> OwnedSemaphorePermit {
+        let origin = self.origin(url);
+        let permit = origin
+            .connections
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+
+        let interval = self
+            .interval
+            .max(crawl_delay.unwrap_or_default())
+            .min(MAX_WAIT);
+        let turn = {
+            let mut schedule = origin.schedule.lock().unwrap();
+            let turn = schedule.next_fetch.max(Instant::now());
+            schedule.next_fetch = turn.checked_add(interval).unwrap_or(turn);
+
+            turn
+        };
+        trace!("waiting until {turn:?} to fetch {url}");
+        time::sleep_until(turn.into()).await;
+
+        permit
+    }
+
+    /// Back off from the origin of `url` if the response says it's overloaded.
+    pub(crate) fn observe(&self, url: &Url, resp: &Response) {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+
+        self.observe_status(url, status, retry_after);
+    }
+
+    fn observe_status(&self, url: &Url, status: StatusCode, retry_after: Option<Duration>) {
+        let origin = self.origin(url);
+        let mut schedule = origin.schedule.lock().unwrap();
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            let backoff = schedule
+                .backoff
+                .map_or(INITIAL_BACKOFF, |backoff| (backoff * 2).min(MAX_BACKOFF));
+            let wait = retry_after.unwrap_or(backoff).min(MAX_WAIT);
+            warn!("{url} returned {status}, backing off from its origin for {wait:?}");
+
+            let now = Instant::now();
+            schedule.backoff = Some(backoff);
+            schedule.next_fetch = schedule
+                .next_fetch
+                .max(now.checked_add(wait).unwrap_or(now));
+        } else if status.is_success() {
+            schedule.backoff = None;
+        }
+    }
+}
+
+/// Parse a `Retry-After` header, which is either delay seconds or a date,
+/// capped at [`MAX_WAIT`].
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let wait = match value.trim().parse() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value.trim()).ok()?;
+            date.duration_since(SystemTime::now()).unwrap_or_default()
+        }
+    };
+
+    Some(wait.min(MAX_WAIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("https://example.com/page").expect("test URL should parse")
+    }
+
+    fn schedule(politeness: &Politeness) -> (Instant, Option<Duration>) {
+        let origin = politeness.origin(&url());
+        let schedule = origin.schedule.lock().unwrap();
+
+        (schedule.next_fetch, schedule.backoff)
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn retry_after_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+
+        let retry_after = parse_retry_after(&date).expect("date should parse");
+
+        assert!(retry_after > Duration::from_secs(3500));
+        assert!(retry_after <= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn retry_after_in_the_past() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn huge_retry_after_is_capped() {
+        let politeness = Politeness::new(None, None);
+        let retry_after = parse_retry_after("18446744073709551615");
+        assert_eq!(retry_after, Some(MAX_WAIT));
+
+        politeness.observe_status(&url(), StatusCode::TOO_MANY_REQUESTS, retry_after);
+        politeness.observe_status(&url(), StatusCode::TOO_MANY_REQUESTS, Some(Duration::MAX));
+
+        assert!(schedule(&politeness).0 <= Instant::now() + MAX_WAIT);
+    }
+
+    #[test]
+    fn retry_after_garbage() {
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn backoff_doubles_until_success() {
+        let politeness = Politeness::new(None, None);
+
+        politeness.observe_status(&url(), StatusCode::TOO_MANY_REQUESTS, None);
+        assert_eq!(schedule(&politeness).1, Some(INITIAL_BACKOFF));
+
+        politeness.observe_status(&url(), StatusCode::SERVICE_UNAVAILABLE, None);
+        assert_eq!(schedule(&politeness).1, Some(INITIAL_BACKOFF * 2));
+
+        politeness.observe_status(&url(), StatusCode::OK, None);
+        assert_eq!(schedule(&politeness).1, None);
+    }
+
+    #[test]
+    fn retry_after_is_honored() {
+        let politeness = Politeness::new(None, None);
+        let before = Instant::now();
+
+        politeness.observe_status(
+            &url(),
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_secs(300)),
+        );
+
+        assert!(schedule(&politeness).0 >= before + Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn turns_are_spaced_by_the_rate_limit() {
+        let politeness = Politeness::new(Some(2.0), None);
+        let before = Instant::now();
+
+        drop(politeness.acquire(&url(), None).await);
+
+        assert!(schedule(&politeness).0 >= before + Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn crawl_delay_overrides_a_faster_rate_limit() {
+        let politeness = Politeness::new(Some(100.0), None);
+        let before = Instant::now();
+
+        drop(
+            politeness
+                .acquire(&url(), Some(Duration::from_secs(5)))
+                .await,
+        );
+
+        assert!(schedule(&politeness).0 >= before + Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn huge_crawl_delays_are_capped() {
+        let politeness = Politeness::new(None, None);
+
+        drop(politeness.acquire(&url(), Some(Duration::MAX)).await);
+
+        assert!(schedule(&politeness).0 <= Instant::now() + MAX_WAIT);
+    }
+
+    #[tokio::test]
+    async fn connections_are_capped_per_origin() {
+        let politeness = Politeness::new(None, Some(1));
+        let other = Url::parse("https://example.org").expect("test URL should parse");
+
+        let _permit = politeness.acquire(&url(), None).await;
+
+        assert_eq!(politeness.origin(&url()).connections.available_permits(), 0);
+        assert_eq!(politeness.origin(&other).connections.available_permits(), 1);
+    }
+}
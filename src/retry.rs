@@ -0,0 +1,134 @@
+use rand::Rng;
+use std::time::Duration;
+
+use crate::fetch::{ErrorKind, FetchError};
+
+/// When and how often to retry a failed fetch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The most times to try fetching a URL, including the first attempt.
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled for each retry after it.
+    pub base_delay: Duration,
+    /// The longest delay between retries.
+    pub max_delay: Duration,
+    /// The HTTP statuses worth retrying.
+    pub retry_statuses: Vec<u16>,
+    /// Whether to retry when no response was received at all, including
+    /// when the request timed out.
+    pub retry_network_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            retry_statuses: vec![408, 429, 500, 502, 503, 504],
+            retry_network_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that gives up after the first attempt.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Whether to retry after `attempts` attempts ending in `error`.
+    pub(crate) fn should_retry(&self, attempts: u32, error: &FetchError) -> bool {
+        if attempts >= self.max_attempts {
+            return false;
+        }
+
+        match error.kind {
+            ErrorKind::Status(status) => self.retry_statuses.contains(&status),
+            ErrorKind::Network | ErrorKind::Timeout => self.retry_network_errors,
+            ErrorKind::TooLarge => false,
+        }
+    }
+
+    /// How long to wait before retrying after `attempts` attempts.
+    ///
+    /// The delay grows exponentially, with a random half of it shaved off
+    /// so that workers retrying together don't stay in lockstep.
+    pub(crate) fn delay(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        delay / 2 + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(kind: ErrorKind) -> FetchError {
+        FetchError {
+            kind,
+            message: "test error".to_string(),
+        }
+    }
+
+    #[test]
+    fn retries_retryable_errors_until_out_of_attempts() {
+        let policy = RetryPolicy::default();
+        let unavailable = error(ErrorKind::Status(503));
+
+        assert!(policy.should_retry(1, &unavailable));
+        assert!(policy.should_retry(2, &unavailable));
+        assert!(!policy.should_retry(3, &unavailable));
+    }
+
+    #[test]
+    fn doesnt_retry_client_errors() {
+        let policy = RetryPolicy::default();
+
+        assert!(!policy.should_retry(1, &error(ErrorKind::Status(404))));
+    }
+
+    #[test]
+    fn network_errors_can_be_left_alone() {
+        let policy = RetryPolicy {
+            retry_network_errors: false,
+            ..RetryPolicy::default()
+        };
+
+        assert!(!policy.should_retry(1, &error(ErrorKind::Network)));
+        assert!(!policy.should_retry(1, &error(ErrorKind::Timeout)));
+    }
+
+    #[test]
+    fn never_retries() {
+        assert!(!RetryPolicy::never().should_retry(1, &error(ErrorKind::Network)));
+    }
+
+    #[test]
+    fn delay_grows_exponentially_with_jitter() {
+        let policy = RetryPolicy::default();
+
+        for (attempts, full_delay) in [(1, 500), (2, 1000), (3, 2000)] {
+            let full_delay = Duration::from_millis(full_delay);
+            let delay = policy.delay(attempts);
+
+            assert!(delay >= full_delay / 2);
+            assert!(delay < full_delay);
+        }
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.delay(100) < policy.max_delay);
+    }
+}
@@ -0,0 +1,591 @@
+use reqwest::{
+    header::{HeaderMap, LOCATION},
+    Response, StatusCode,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{OnceCell, OwnedSemaphorePermit};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::fetch::{read_limited, Fetcher};
+
+/// The robots.txt user-agent token used unless configured otherwise.
+pub const DEFAULT_ROBOTS_AGENT: &str = "spdrs";
+
+/// The longest crawl delay honored, so a site can't stall the crawl by
+/// asking for days between requests.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// The most of a robots.txt file to read, after decompression, which is the
+/// least RFC 9309 asks parsers to handle.
+const MAX_ROBOTS_SIZE: u64 = 500 * 1024;
+
+/// The most redirects followed for robots.txt, whatever the crawl follows,
+/// which is the least RFC 9309 asks crawlers to follow.
+const MAX_ROBOTS_REDIRECTS: usize = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// The robots.txt rules that apply to one user-agent on one origin.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Rules {
+    rules: Vec<Rule>,
+    pub(crate) crawl_delay: Option<Duration>,
+}
+
+impl Rules {
+    /// Rules that disallow everything, used when robots.txt is unreachable.
+    fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parse the groups of a robots.txt file that apply to `agent`.
+    ///
+    /// Groups naming the agent take precedence over `*` groups, and all
+    /// groups naming the same agent are merged, as described in RFC 9309.
+    pub(crate) fn parse(text: &str, agent: &str) -> Self {
+        let agent = agent.to_lowercase();
+        let mut specific = Rules::default();
+        let mut wildcard = Rules::default();
+        let mut matched_specific = false;
+
+        let mut group_agents: Vec<String> = vec![];
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    let group_agent = value.to_lowercase();
+                    matched_specific |= group_agent == agent;
+                    group_agents.push(group_agent);
+                }
+                "allow" | "disallow" | "crawl-delay" => {
+                    in_rules = true;
+
+                    for group_agent in &group_agents {
+                        let target = if *group_agent == agent {
+                            &mut specific
+                        } else if group_agent == "*" {
+                            &mut wildcard
+                        } else {
+                            continue;
+                        };
+
+                        if key == "crawl-delay" {
+                            target.crawl_delay = value
+                                .parse()
+                                .ok()
+                                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                                .map(|delay| delay.min(MAX_CRAWL_DELAY));
+                        } else if !value.is_empty() {
+                            target.rules.push(Rule {
+                                allow: key == "allow",
+                                pattern: value.to_string(),
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if matched_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    /// Whether the rules let us fetch `url`.
+    ///
+    /// The longest matching pattern wins, with `Allow` winning ties.
+    pub(crate) fn is_allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Match a robots.txt path pattern, which may use `*` to match any run of
+/// characters and a trailing `$` to anchor it to the end of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+/// Fetches and remembers the robots.txt rules of every origin in a crawl.
+#[derive(Debug)]
+pub(crate) struct RobotsCache {
+    agent: String,
+    origins: Mutex<HashMap<String, Arc<OnceCell<Rules>>>>,
+}
+
+impl RobotsCache {
+    pub(crate) fn new(agent: impl Into<String>) -> Self {
+        Self {
+            agent: agent.into(),
+            origins: Mutex::default(),
+        }
+    }
+
+    /// Get the rules for the origin of `url`, fetching them the first time.
+    pub(crate) async fn rules(&self, fetcher: &Fetcher, url: &Url) -> Rules {
+        let origin = url.origin().ascii_serialization();
+        let cell = self
+            .origins
+            .lock()
+            .unwrap()
+            .entry(origin)
+            .or_default()
+            .clone();
+
+        cell.get_or_init(|| self.fetch(fetcher, url)).await.clone()
+    }
+
+    /// Fetch robots.txt, treating a missing file as allowing everything and
+    /// an unreachable one as disallowing everything, as RFC 9309 suggests.
+    ///
+    /// The request waits its turn like any other to the origin.
+    async fn fetch(&self, fetcher: &Fetcher, url: &Url) -> Rules {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return Rules::default();
+        };
+        let Some((resp, _permit)) = Self::get(fetcher, robots_url.clone()).await else {
+            return Rules::disallow_all();
+        };
+
+        let status = resp.status();
+        if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+            debug!("no robots.txt at {robots_url} ({status})");
+            return Rules::default();
+        }
+        if !status.is_success() {
+            warn!("Error fetching {robots_url} ({status}), disallowing origin");
+            return Rules::disallow_all();
+        }
+
+        match read_limited(resp, Some(MAX_ROBOTS_SIZE), true).await {
+            Ok(body) => {
+                if body.truncated {
+                    warn!("Only parsing the start of {robots_url}, which is too large");
+                }
+                Rules::parse(&String::from_utf8_lossy(&body.bytes), &self.agent)
+            }
+            Err(error) => {
+                warn!("Error reading {robots_url} ({error}), disallowing origin");
+                Rules::disallow_all()
+            }
+        }
+    }
+
+    /// Request `url`, following redirects ourselves so that they're followed
+    /// even when the crawl doesn't follow any, and returning the response
+    /// with the connection permit to hold while reading it.
+    async fn get(fetcher: &Fetcher, mut url: Url) -> Option<(Response, OwnedSemaphorePermit)> {
+        for _ in 0..=MAX_ROBOTS_REDIRECTS {
+            let permit = fetcher.politeness.acquire(&url, None).await;
+            debug!("fetching {url}");
+
+            let resp = match fetcher.client.get(url.clone()).send().await {
+                Ok(resp) => resp,
+                Err(error) => {
+                    warn!("Error fetching {url} ({error}), disallowing origin");
+                    return None;
+                }
+            };
+            fetcher.politeness.observe(&url, &resp);
+
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| resp.url().join(location).ok());
+            match location {
+                Some(location) if resp.status().is_redirection() => url = location,
+                _ => return Some((resp, permit)),
+            }
+        }
+
+        warn!("Too many redirects fetching {url}, disallowing origin");
+        None
+    }
+}
+
+/// The indexing directives for a page, from `<meta name="robots">` tags and
+/// `X-Robots-Tag` headers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MetaRobots {
+    /// The page asks not to be indexed.
+    pub noindex: bool,
+    /// The page asks for none of its links to be followed.
+    pub nofollow: bool,
+}
+
+impl MetaRobots {
+    /// The directives for `agent` in the `content` of `<meta name="...">`
+    /// tags, given as name and content pairs, and in `X-Robots-Tag` headers.
+    ///
+    /// Meta tags apply if they're named `robots` or after the agent, and
+    /// headers apply unless they're prefixed with another agent's name, like
+    /// `otherbot: noindex`.
+    pub(crate) fn parse<'a>(
+        meta: impl IntoIterator<Item = (&'a str, &'a str)>,
+        headers: &HeaderMap,
+        agent: &str,
+    ) -> Self {
+        let mut directives = Self::default();
+
+        for (name, content) in meta {
+            if name.eq_ignore_ascii_case("robots") || name.eq_ignore_ascii_case(agent) {
+                directives.add(content);
+            }
+        }
+
+        for value in headers.get_all("x-robots-tag") {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            match value.split_once(':') {
+                Some((prefix, rest)) if !prefix.contains(',') => {
+                    if prefix.trim().eq_ignore_ascii_case(agent) {
+                        directives.add(rest);
+                    }
+                }
+                _ => directives.add(value),
+            }
+        }
+
+        directives
+    }
+
+    /// Add the directives in a comma-separated list, like `noindex, follow`.
+    fn add(&mut self, list: &str) {
+        for directive in list.split(',').map(str::trim) {
+            if directive.eq_ignore_ascii_case("noindex") {
+                self.noindex = true;
+            } else if directive.eq_ignore_ascii_case("nofollow") {
+                self.nofollow = true;
+            } else if directive.eq_ignore_ascii_case("none") {
+                self.noindex = true;
+                self.nofollow = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::ContentPolicy,
+        fetch::tests::{sequential_server, stalling_server},
+        politeness::Politeness,
+        retry::RetryPolicy,
+        ClientConfig,
+    };
+    use reqwest::Client;
+    use std::time::Duration;
+
+    fn fetcher(politeness: Politeness) -> Fetcher {
+        fetcher_with(Client::new(), politeness)
+    }
+
+    fn fetcher_with(client: Client, politeness: Politeness) -> Fetcher {
+        Fetcher::new(
+            client,
+            politeness,
+            RetryPolicy::never(),
+            ContentPolicy::default(),
+            None,
+        )
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com")
+            .and_then(|base| base.join(path))
+            .expect("test URL should parse")
+    }
+
+    #[test]
+    fn empty_file_allows_everything() {
+        let rules = Rules::parse("", "spdrs");
+
+        assert!(rules.is_allowed(&url("/anything")));
+    }
+
+    #[test]
+    fn specific_agent_takes_precedence_over_wildcard() {
+        let text = "
+User-agent: *
+Disallow: /
+
+User-agent: spdrs
+Disallow: /private/
+";
+        let rules = Rules::parse(text, "SPDRS");
+
+        assert!(rules.is_allowed(&url("/public/")));
+        assert!(!rules.is_allowed(&url("/private/page.html")));
+    }
+
+    #[test]
+    fn wildcard_applies_to_other_agents() {
+        let text = "
+User-agent: otherbot
+User-agent: *
+Disallow: /tmp # scratch space
+Crawl-delay: 2.5
+";
+        let rules = Rules::parse(text, "spdrs");
+
+        assert!(!rules.is_allowed(&url("/tmp/file")));
+        assert!(rules.is_allowed(&url("/index.html")));
+        assert_eq!(rules.crawl_delay, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn huge_crawl_delays_are_capped() {
+        let text = "
+User-agent: *
+Crawl-delay: 1e18
+";
+        let rules = Rules::parse(text, "spdrs");
+
+        assert_eq!(rules.crawl_delay, Some(MAX_CRAWL_DELAY));
+    }
+
+    #[test]
+    fn longest_match_wins() {
+        let text = "
+User-agent: *
+Disallow: /docs/
+Allow: /docs/public/
+Disallow: /docs/public/drafts
+";
+        let rules = Rules::parse(text, "spdrs");
+
+        assert!(!rules.is_allowed(&url("/docs/private.html")));
+        assert!(rules.is_allowed(&url("/docs/public/page.html")));
+        assert!(!rules.is_allowed(&url("/docs/public/drafts/page.html")));
+    }
+
+    #[test]
+    fn allow_wins_ties() {
+        let text = "
+User-agent: *
+Disallow: /page
+Allow: /page
+";
+        let rules = Rules::parse(text, "spdrs");
+
+        assert!(rules.is_allowed(&url("/page")));
+    }
+
+    #[test]
+    fn wildcards_and_anchors() {
+        let text = "
+User-agent: *
+Disallow: /*.pdf$
+Disallow: /*?session=
+";
+        let rules = Rules::parse(text, "spdrs");
+
+        assert!(!rules.is_allowed(&url("/files/report.pdf")));
+        assert!(rules.is_allowed(&url("/files/report.pdf.html")));
+        assert!(!rules.is_allowed(&url("/cart?session=123")));
+        assert!(rules.is_allowed(&url("/cart")));
+    }
+
+    fn robots_tags<const N: usize>(values: [&str; N]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(
+                "x-robots-tag",
+                value.parse().expect("test header should parse"),
+            );
+        }
+
+        headers
+    }
+
+    #[test]
+    fn meta_robots_for_all_agents() {
+        let meta = [
+            ("viewport", "width=device-width"),
+            ("ROBOTS", "NoIndex, follow"),
+        ];
+
+        let directives = MetaRobots::parse(meta, &HeaderMap::new(), "spdrs");
+
+        assert_eq!(
+            directives,
+            MetaRobots {
+                noindex: true,
+                nofollow: false,
+            }
+        );
+    }
+
+    #[test]
+    fn meta_robots_for_named_agents() {
+        let meta = [("otherbot", "none"), ("spdrs", "nofollow")];
+
+        let directives = MetaRobots::parse(meta, &HeaderMap::new(), "spdrs");
+
+        assert_eq!(
+            directives,
+            MetaRobots {
+                noindex: false,
+                nofollow: true,
+            }
+        );
+    }
+
+    #[test]
+    fn x_robots_tag_headers() {
+        let headers = robots_tags(["otherbot: noindex", "spdrs: nofollow", "noarchive, none"]);
+
+        assert_eq!(
+            MetaRobots::parse([], &headers, "spdrs"),
+            MetaRobots {
+                noindex: true,
+                nofollow: true,
+            }
+        );
+        assert_eq!(
+            MetaRobots::parse([], &robots_tags(["otherbot: noindex, nofollow"]), "spdrs"),
+            MetaRobots::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_start_of_large_files_is_read() {
+        let text = format!(
+            "User-agent: *\nDisallow: /early\n{}\nDisallow: /late\n",
+            "#".repeat(MAX_ROBOTS_SIZE as usize)
+        );
+        let origin = stalling_server(format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{text}",
+            text.len()
+        ));
+
+        let rules = RobotsCache::new("spdrs")
+            .rules(&fetcher(Politeness::new(None, None)), &origin)
+            .await;
+
+        assert!(!rules.is_allowed(&origin.join("/early").expect("test URL should join")));
+        assert!(rules.is_allowed(&origin.join("/late").expect("test URL should join")));
+    }
+
+    #[tokio::test]
+    async fn robots_txt_waits_for_a_connection() {
+        let origin = stalling_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let fetcher = fetcher(Politeness::new(None, Some(1)));
+        let permit = fetcher.politeness.acquire(&origin, None).await;
+
+        let cache = RobotsCache::new("spdrs");
+        let waiting =
+            tokio::time::timeout(Duration::from_millis(100), cache.rules(&fetcher, &origin));
+        assert!(waiting.await.is_err());
+
+        drop(permit);
+        let rules = cache.rules(&fetcher, &origin).await;
+        assert!(rules.is_allowed(&origin));
+    }
+
+    #[tokio::test]
+    async fn redirects_are_followed_when_the_crawl_follows_none() {
+        let origin = sequential_server(vec![
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: /real-robots.txt\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 32\r\nConnection: close\r\n\r\n\
+             User-agent: *\nDisallow: /private",
+        ]);
+        let client = ClientConfig {
+            max_redirects: 0,
+            ..ClientConfig::default()
+        }
+        .build()
+        .expect("test client should build");
+
+        let rules = RobotsCache::new("spdrs")
+            .rules(&fetcher_with(client, Politeness::new(None, None)), &origin)
+            .await;
+
+        assert!(!rules.is_allowed(&origin.join("/private").expect("test URL should join")));
+        assert!(rules.is_allowed(&origin.join("/public").expect("test URL should join")));
+    }
+
+    #[tokio::test]
+    async fn endless_redirects_disallow_everything() {
+        let loop_back = "HTTP/1.1 302 Found\r\nLocation: /robots.txt\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n";
+        let origin = sequential_server(vec![loop_back; MAX_ROBOTS_REDIRECTS + 1]);
+        let client = ClientConfig {
+            max_redirects: 0,
+            ..ClientConfig::default()
+        }
+        .build()
+        .expect("test client should build");
+
+        let rules = RobotsCache::new("spdrs")
+            .rules(&fetcher_with(client, Politeness::new(None, None)), &origin)
+            .await;
+
+        assert_eq!(rules, Rules::disallow_all());
+    }
+}
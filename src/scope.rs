@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Error, Result};
+use std::{fmt, str::FromStr};
+use url::Url;
+
+/// A rule for which hosts a crawl may follow links to.
+///
+/// Rules can be parsed from strings like `example.com`, `example.com:8080`
+/// and `*.example.com`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostRule {
+    /// Exactly this host, on any port.
+    Host(String),
+    /// Exactly this host, on this port.
+    HostPort(String, u16),
+    /// Any subdomain of this domain, but not the domain itself.
+    Subdomains(String),
+    /// Any host under this registrable domain (eTLD+1), including itself,
+    /// e.g. `example.co.uk` covers `example.co.uk` and `www.example.co.uk`.
+    RegistrableDomain(String),
+}
+
+impl HostRule {
+    /// A rule for exactly the host of `url`, and its port if it has a
+    /// non-default one.
+    pub fn for_url(url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_string();
+
+        Some(match url.port() {
+            Some(port) => Self::HostPort(host, port),
+            None => Self::Host(host),
+        })
+    }
+
+    /// A rule for every host under the registrable domain of `url`.
+    ///
+    /// Hosts without one, like IP addresses and `localhost`, get a rule for
+    /// exactly that host instead.
+    pub fn same_site_as(url: &Url) -> Option<Self> {
+        let host = url.host_str()?;
+
+        Some(match registrable_domain(host) {
+            Some(domain) => Self::RegistrableDomain(domain.to_string()),
+            None => Self::Host(host.to_string()),
+        })
+    }
+
+    /// Whether the host of `url` is covered by this rule.
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        match self {
+            Self::Host(allowed) => host == allowed,
+            Self::HostPort(allowed, port) => {
+                host == allowed && url.port_or_known_default() == Some(*port)
+            }
+            Self::Subdomains(domain) => host
+                .strip_suffix(domain.as_str())
+                .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+            Self::RegistrableDomain(domain) => registrable_domain(host) == Some(domain.as_str()),
+        }
+    }
+}
+
+/// The registrable domain of `host` according to the public suffix list.
+fn registrable_domain(host: &str) -> Option<&str> {
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return None;
+    }
+
+    psl::domain_str(host)
+}
+
+impl FromStr for HostRule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        let (wildcard, authority) = match rule.strip_prefix("*.") {
+            Some(authority) => (true, authority),
+            None => (false, rule),
+        };
+
+        // Let the URL parser normalise case, IDNs and default ports for us.
+        let url = Url::parse(&format!("http://{authority}/"))
+            .map_err(|error| anyhow!("Invalid host rule {rule:?} ({error})"))?;
+        let host = url
+            .host_str()
+            .filter(|_| url.path() == "/" && url.username().is_empty())
+            .ok_or(anyhow!("Invalid host rule {rule:?}"))?
+            .to_string();
+
+        match (wildcard, url.port()) {
+            (true, None) => Ok(Self::Subdomains(host)),
+            (true, Some(_)) => Err(anyhow!("Wildcard host rule {rule:?} can't have a port")),
+            (false, None) if authority.ends_with(":80") => Ok(Self::HostPort(host, 80)),
+            (false, None) => Ok(Self::Host(host)),
+            (false, Some(port)) => Ok(Self::HostPort(host, port)),
+        }
+    }
+}
+
+impl fmt::Display for HostRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Host(host) => write!(f, "{host}"),
+            Self::HostPort(host, port) => write!(f, "{host}:{port}"),
+            Self::Subdomains(domain) => write!(f, "*.{domain}"),
+            Self::RegistrableDomain(domain) => write!(f, "{domain} and its subdomains"),
+        }
+    }
+}
+
+/// The set of hosts a crawl may follow links to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Scope {
+    rules: Vec<HostRule>,
+}
+
+impl Scope {
+    pub(crate) fn new(rules: Vec<HostRule>) -> Self {
+        Self { rules }
+    }
+
+    pub(crate) fn rules(&self) -> &[HostRule] {
+        &self.rules
+    }
+
+    /// Whether `url` is an HTTP(S) URL on a host matching any of the rules.
+    pub(crate) fn contains(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https") && self.rules.iter().any(|rule| rule.matches(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).expect("test URL should parse")
+    }
+
+    fn rule(rule: &str) -> HostRule {
+        rule.parse().expect("test rule should parse")
+    }
+
+    #[test]
+    fn parse_rules() {
+        assert_eq!(
+            rule("Example.COM"),
+            HostRule::Host("example.com".to_string())
+        );
+        assert_eq!(
+            rule("example.com:8080"),
+            HostRule::HostPort("example.com".to_string(), 8080)
+        );
+        assert_eq!(
+            rule("example.com:80"),
+            HostRule::HostPort("example.com".to_string(), 80)
+        );
+        assert_eq!(
+            rule("*.example.com"),
+            HostRule::Subdomains("example.com".to_string())
+        );
+        assert!("*.example.com:8080".parse::<HostRule>().is_err());
+        assert!("example.com/path".parse::<HostRule>().is_err());
+        assert!("".parse::<HostRule>().is_err());
+    }
+
+    #[test]
+    fn exact_host_rejects_lookalikes() {
+        let rule = rule("example.com");
+
+        assert!(rule.matches(&url("https://example.com/")));
+        assert!(rule.matches(&url("http://example.com:8080/")));
+        assert!(!rule.matches(&url("https://example.com.attacker.net/")));
+        assert!(!rule.matches(&url("https://example.community/")));
+        assert!(!rule.matches(&url("https://www.example.com/")));
+        assert!(!rule.matches(&url("https://notexample.com/")));
+    }
+
+    #[test]
+    fn host_and_port() {
+        let rule = rule("localhost:8000");
+
+        assert!(rule.matches(&url("http://localhost:8000/page.html")));
+        assert!(!rule.matches(&url("http://localhost/page.html")));
+        assert!(!rule.matches(&url("http://localhost:8001/page.html")));
+    }
+
+    #[test]
+    fn default_ports_match_explicit_ones() {
+        let rule = HostRule::HostPort("example.com".to_string(), 443);
+
+        assert!(rule.matches(&url("https://example.com/")));
+        assert!(!rule.matches(&url("http://example.com/")));
+    }
+
+    #[test]
+    fn subdomain_wildcard() {
+        let rule = rule("*.example.com");
+
+        assert!(rule.matches(&url("https://www.example.com/")));
+        assert!(rule.matches(&url("https://a.b.example.com/")));
+        assert!(!rule.matches(&url("https://example.com/")));
+        assert!(!rule.matches(&url("https://wwwexample.com/")));
+        assert!(!rule.matches(&url("https://www.example.com.attacker.net/")));
+    }
+
+    #[test]
+    fn registrable_domain() {
+        let rule = HostRule::same_site_as(&url("https://www.example.co.uk/"))
+            .expect("URL should have a host");
+
+        assert_eq!(
+            rule,
+            HostRule::RegistrableDomain("example.co.uk".to_string())
+        );
+        assert!(rule.matches(&url("https://example.co.uk/")));
+        assert!(rule.matches(&url("https://shop.example.co.uk/")));
+        assert!(!rule.matches(&url("https://other.co.uk/")));
+        assert!(!rule.matches(&url("https://example.co.uk.attacker.net/")));
+    }
+
+    #[test]
+    fn same_site_without_registrable_domain() {
+        assert_eq!(
+            HostRule::same_site_as(&url("http://127.0.0.1:8000/")),
+            Some(HostRule::Host("127.0.0.1".to_string()))
+        );
+    }
+
+    #[test]
+    fn rule_for_url() {
+        assert_eq!(
+            HostRule::for_url(&url("https://example.com/page")),
+            Some(HostRule::Host("example.com".to_string()))
+        );
+        assert_eq!(
+            HostRule::for_url(&url("http://localhost:8000/")),
+            Some(HostRule::HostPort("localhost".to_string(), 8000))
+        );
+    }
+
+    #[test]
+    fn scope_only_contains_http() {
+        let scope = Scope::new(vec![rule("example.com"), rule("cdn.example.net")]);
+
+        assert!(scope.contains(&url("https://example.com/")));
+        assert!(scope.contains(&url("http://cdn.example.net/image.png")));
+        assert!(!scope.contains(&url("ftp://example.com/")));
+        assert!(!scope.contains(&url("https://example.org/")));
+    }
+}